@@ -0,0 +1,51 @@
+/// Per-map sky and fog settings, the colors themselves come from the day cycle.
+#[derive(Copy, Clone, Debug)]
+pub struct Atmosphere {
+    /// The fraction of light scattered away per unit travelled, 0.0 disables fog.
+    pub fog_density: f32,
+    /// Whether the sun's disc is drawn in the sky.
+    pub sun_disc: bool,
+}
+
+/// The fog factor at which geometry is considered fully hidden.
+const HIDDEN: f32 = 0.01;
+
+impl Atmosphere {
+    pub fn new() -> Self {
+        let mut atmosphere = Atmosphere {
+            fog_density: 0.0,
+            sun_disc: true,
+        };
+        atmosphere.set_visibility(80.0);
+        atmosphere
+    }
+
+    /// Sets the fog density so geometry is hidden at the given distance, 0.0 disables fog.
+    pub fn set_visibility(&mut self, distance: f32) {
+        self.fog_density = if distance > 0.0 { -HIDDEN.ln() / distance } else { 0.0 };
+    }
+
+    /// How much of the light from an object at the given distance reaches the camera, between
+    /// 0.0 and 1.0.
+    pub fn fog_factor(&self, distance: f32) -> f32 {
+        (-self.fog_density * distance).exp()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use atmosphere::Atmosphere;
+
+    #[test]
+    fn visibility_hides_geometry_at_distance() {
+        let mut atmosphere = Atmosphere::new();
+        atmosphere.set_visibility(100.0);
+
+        assert!((atmosphere.fog_factor(0.0) - 1.0).abs() < 0.0001);
+        assert!((atmosphere.fog_factor(100.0) - 0.01).abs() < 0.0001);
+        assert!(atmosphere.fog_factor(50.0) > atmosphere.fog_factor(60.0));
+
+        atmosphere.set_visibility(0.0);
+        assert_eq!(atmosphere.fog_factor(1000.0), 1.0);
+    }
+}
@@ -0,0 +1,288 @@
+use sc_input_data::{Button, CursorMode};
+
+/// A layer of the game that takes input, these stack on top of each other with gameplay at the
+/// bottom.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum InputContext {
+    Gameplay,
+    Menu,
+    Console,
+    Chat,
+}
+
+impl InputContext {
+    pub fn cursor_mode(&self) -> CursorMode {
+        match *self {
+            // Chat sits on top of gameplay, the player can keep looking around while typing
+            InputContext::Gameplay | InputContext::Chat => CursorMode::Grabbed,
+            InputContext::Menu | InputContext::Console => CursorMode::Free,
+        }
+    }
+
+    pub fn takes_text(&self) -> bool {
+        *self == InputContext::Console || *self == InputContext::Chat
+    }
+
+    /// If the local world stops updating while this context is on top.
+    pub fn pauses(&self) -> bool {
+        *self == InputContext::Menu
+    }
+}
+
+/// What a button does in the game.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Action {
+    /// Kept in the input state for as long as the button is down.
+    Hold(Button),
+    /// Puts a context on top of the stack.
+    Open(InputContext),
+    /// Removes the top context.
+    Close,
+    /// Sends what was typed and removes the top context.
+    Submit,
+    /// Ends the session.
+    Quit,
+}
+
+/// How a context handles a button.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Binding {
+    Action(Action),
+    /// Hides the button from the contexts below.
+    Consume,
+    /// Leaves the button to the context below.
+    Pass,
+}
+
+/// The bindings of a single context, buttons without one get the same default binding.
+pub struct ActionMap {
+    bindings: Vec<(Button, Binding)>,
+    unbound: Binding,
+    passes_pointer: bool,
+}
+
+impl ActionMap {
+    pub fn new(unbound: Binding) -> Self {
+        ActionMap {
+            bindings: Vec::new(),
+            unbound: unbound,
+            passes_pointer: false,
+        }
+    }
+
+    /// The bindings a context starts out with.
+    pub fn default_for(context: InputContext) -> Self {
+        let close = Binding::Action(Action::Close);
+
+        match context {
+            InputContext::Gameplay => {
+                let mut map = ActionMap::new(Binding::Consume);
+                for &button in &[
+                    Button::MoveForward, Button::MoveBackward, Button::MoveRight,
+                    Button::MoveLeft, Button::Fire, Button::Aim,
+                ] {
+                    map.bind(button, Binding::Action(Action::Hold(button)));
+                }
+                map.bind(Button::Menu, Binding::Action(Action::Open(InputContext::Menu)));
+                map.bind(Button::Console, Binding::Action(Action::Open(InputContext::Console)));
+                map.bind(Button::Chat, Binding::Action(Action::Open(InputContext::Chat)));
+                map.set_passes_pointer(true);
+                map
+            },
+            InputContext::Menu => {
+                let mut map = ActionMap::new(Binding::Consume);
+                map.bind(Button::Menu, close);
+                map.bind(Button::Confirm, Binding::Action(Action::Quit));
+                map
+            },
+            InputContext::Console => {
+                let mut map = ActionMap::new(Binding::Consume);
+                map.bind(Button::Menu, close);
+                map.bind(Button::Console, close);
+                map.bind(Button::Confirm, Binding::Action(Action::Submit));
+                map
+            },
+            InputContext::Chat => {
+                // Looking around, firing and aiming still work mid-sentence
+                let mut map = ActionMap::new(Binding::Consume);
+                map.bind(Button::Menu, close);
+                map.bind(Button::Confirm, Binding::Action(Action::Submit));
+                map.bind(Button::Fire, Binding::Pass);
+                map.bind(Button::Aim, Binding::Pass);
+                map.set_passes_pointer(true);
+                map
+            },
+        }
+    }
+
+    pub fn bind(&mut self, button: Button, binding: Binding) {
+        self.bindings.retain(|&(b, _)| b != button);
+        self.bindings.push((button, binding));
+    }
+
+    pub fn get(&self, button: Button) -> Binding {
+        self.bindings.iter()
+            .find(|&&(b, _)| b == button)
+            .map(|&(_, binding)| binding)
+            .unwrap_or(self.unbound)
+    }
+
+    /// Sets if mouse motion and scrolling go on to the context below, rather than stopping here.
+    pub fn set_passes_pointer(&mut self, passes: bool) {
+        self.passes_pointer = passes;
+    }
+
+    pub fn passes_pointer(&self) -> bool {
+        self.passes_pointer
+    }
+}
+
+/// The stack of active input contexts, each with its own action map.
+pub struct InputContexts {
+    stack: Vec<InputContext>,
+    gameplay: ActionMap,
+    menu: ActionMap,
+    console: ActionMap,
+    chat: ActionMap,
+}
+
+impl InputContexts {
+    pub fn new() -> Self {
+        InputContexts {
+            stack: vec![InputContext::Gameplay],
+            gameplay: ActionMap::default_for(InputContext::Gameplay),
+            menu: ActionMap::default_for(InputContext::Menu),
+            console: ActionMap::default_for(InputContext::Console),
+            chat: ActionMap::default_for(InputContext::Chat),
+        }
+    }
+
+    pub fn top(&self) -> InputContext {
+        *self.stack.last().unwrap()
+    }
+
+    /// Opens the context on top of the others, a context that's already open is moved to the top.
+    /// Gameplay always stays at the bottom, pushing it does nothing and returns false.
+    pub fn push(&mut self, context: InputContext) -> bool {
+        if context == InputContext::Gameplay {
+            return false;
+        }
+
+        self.stack.retain(|&c| c != context);
+        self.stack.push(context);
+        true
+    }
+
+    /// Closes the top context, returning false if only gameplay is left and nothing was closed.
+    pub fn pop(&mut self) -> bool {
+        if self.stack.len() > 1 {
+            self.stack.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn map(&self, context: InputContext) -> &ActionMap {
+        match context {
+            InputContext::Gameplay => &self.gameplay,
+            InputContext::Menu => &self.menu,
+            InputContext::Console => &self.console,
+            InputContext::Chat => &self.chat,
+        }
+    }
+
+    pub fn map_mut(&mut self, context: InputContext) -> &mut ActionMap {
+        match context {
+            InputContext::Gameplay => &mut self.gameplay,
+            InputContext::Menu => &mut self.menu,
+            InputContext::Console => &mut self.console,
+            InputContext::Chat => &mut self.chat,
+        }
+    }
+
+    /// Finds what the button does, starting at the top context and going down for as long as
+    /// the contexts pass it on.
+    pub fn resolve(&self, button: Button) -> Option<Action> {
+        for &context in self.stack.iter().rev() {
+            match self.map(context).get(button) {
+                Binding::Action(action) => return Some(action),
+                Binding::Consume => return None,
+                Binding::Pass => (),
+            }
+        }
+        None
+    }
+
+    /// If mouse motion and scrolling make it through every open context to gameplay.
+    pub fn pointer_reaches_gameplay(&self) -> bool {
+        self.stack.iter().all(|&context| self.map(context).passes_pointer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_input_data::Button;
+    use context::{InputContexts, InputContext, Action};
+
+    #[test]
+    fn escape_depends_on_the_top_context() {
+        let mut contexts = InputContexts::new();
+        assert_eq!(contexts.resolve(Button::Menu), Some(Action::Open(InputContext::Menu)));
+
+        contexts.push(InputContext::Console);
+        assert_eq!(contexts.resolve(Button::Menu), Some(Action::Close));
+
+        assert!(contexts.pop());
+        assert!(!contexts.pop());
+        assert_eq!(contexts.top(), InputContext::Gameplay);
+    }
+
+    #[test]
+    fn gameplay_stays_at_the_bottom() {
+        let mut contexts = InputContexts::new();
+        contexts.push(InputContext::Menu);
+
+        assert!(!contexts.push(InputContext::Gameplay));
+        assert_eq!(contexts.top(), InputContext::Menu);
+        assert!(contexts.pop());
+        assert!(!contexts.pop());
+    }
+
+    #[test]
+    fn menu_stops_the_pointer_and_quits() {
+        let mut contexts = InputContexts::new();
+        assert!(contexts.pointer_reaches_gameplay());
+
+        contexts.push(InputContext::Chat);
+        assert!(contexts.pointer_reaches_gameplay());
+
+        contexts.push(InputContext::Menu);
+        assert!(!contexts.pointer_reaches_gameplay());
+        assert_eq!(contexts.resolve(Button::Confirm), Some(Action::Quit));
+    }
+
+    #[test]
+    fn chat_passes_fire_through() {
+        let mut contexts = InputContexts::new();
+        contexts.push(InputContext::Chat);
+
+        assert_eq!(contexts.resolve(Button::Fire), Some(Action::Hold(Button::Fire)));
+        assert_eq!(contexts.resolve(Button::MoveForward), None);
+
+        contexts.push(InputContext::Menu);
+        assert_eq!(contexts.resolve(Button::Fire), None);
+    }
+
+    #[test]
+    fn enter_submits_text_fields() {
+        let mut contexts = InputContexts::new();
+        assert_eq!(contexts.resolve(Button::Confirm), None);
+
+        contexts.push(InputContext::Chat);
+        assert_eq!(contexts.resolve(Button::Confirm), Some(Action::Submit));
+
+        contexts.push(InputContext::Console);
+        assert_eq!(contexts.resolve(Button::Confirm), Some(Action::Submit));
+    }
+}
@@ -0,0 +1,198 @@
+use cgmath::{Vector3, Rad, Angle, InnerSpace};
+use lighting::Lighting;
+
+/// A looping color gradient, keyed on a time of day between 0.0 and 1.0.
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    keys: Vec<(f32, Vector3<f32>)>,
+}
+
+impl Gradient {
+    /// Creates a gradient from keys, which will be sorted by time.
+    ///
+    /// # Panics
+    /// Panics if no keys are given.
+    pub fn new(mut keys: Vec<(f32, Vector3<f32>)>) -> Self {
+        assert!(!keys.is_empty(), "a gradient needs at least one key");
+        keys.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+
+        Gradient {
+            keys: keys,
+        }
+    }
+
+    pub fn sample(&self, time: f32) -> Vector3<f32> {
+        let time = wrap(time);
+
+        // Find the keys around the time, wrapping around the end of the day
+        let next_i = self.keys.iter().position(|k| k.0 > time).unwrap_or(0);
+        let prev_i = if next_i == 0 { self.keys.len() - 1 } else { next_i - 1 };
+        let (prev_t, prev) = self.keys[prev_i];
+        let (next_t, next) = self.keys[next_i];
+
+        // Get the distances as if the day continued past its end
+        let span = wrap(next_t - prev_t);
+        let offset = wrap(time - prev_t);
+        if span == 0.0 {
+            return prev;
+        }
+
+        prev.lerp(next, offset / span)
+    }
+}
+
+/// The world's clock, driving the sun, sky and ambient light.
+pub struct DayCycle {
+    time_of_day: f32,
+    day_length: f32,
+    sky: Gradient,
+    horizon: Gradient,
+    sun: Gradient,
+    ambient: Gradient,
+}
+
+impl DayCycle {
+    pub fn new(day_length: f32) -> Self {
+        let night_sky = Vector3::new(0.01, 0.01, 0.03);
+        let dawn_sky = Vector3::new(0.8, 0.45, 0.3);
+        let day_sky = Vector3::new(0.4, 0.6, 0.9);
+        let night_horizon = Vector3::new(0.03, 0.03, 0.06);
+        let dawn_horizon = Vector3::new(1.0, 0.6, 0.35);
+        let day_horizon = Vector3::new(0.75, 0.85, 0.95);
+        let night_sun = Vector3::new(0.0, 0.0, 0.0);
+        let dawn_sun = Vector3::new(1.0, 0.5, 0.2);
+        let day_sun = Vector3::new(1.0, 0.95, 0.85);
+
+        DayCycle {
+            time_of_day: 0.35,
+            day_length: day_length,
+            sky: Gradient::new(vec![
+                (0.0, night_sky), (0.2, night_sky), (0.25, dawn_sky), (0.32, day_sky),
+                (0.68, day_sky), (0.75, dawn_sky), (0.8, night_sky),
+            ]),
+            horizon: Gradient::new(vec![
+                (0.0, night_horizon), (0.2, night_horizon), (0.25, dawn_horizon), (0.32, day_horizon),
+                (0.68, day_horizon), (0.75, dawn_horizon), (0.8, night_horizon),
+            ]),
+            sun: Gradient::new(vec![
+                (0.0, night_sun), (0.24, night_sun), (0.27, dawn_sun), (0.35, day_sun),
+                (0.65, day_sun), (0.73, dawn_sun), (0.76, night_sun),
+            ]),
+            ambient: Gradient::new(vec![
+                (0.0, Vector3::new(0.02, 0.02, 0.05)), (0.22, Vector3::new(0.02, 0.02, 0.05)),
+                (0.3, Vector3::new(0.15, 0.15, 0.2)), (0.7, Vector3::new(0.15, 0.15, 0.2)),
+                (0.78, Vector3::new(0.02, 0.02, 0.05)),
+            ]),
+        }
+    }
+
+    /// The time of day, 0.0 is midnight and 0.5 is noon.
+    pub fn time_of_day(&self) -> f32 {
+        self.time_of_day
+    }
+
+    /// Sets the time of day, for starting the cycle at a particular point.
+    pub fn set_time_of_day(&mut self, time_of_day: f32) {
+        self.time_of_day = wrap(time_of_day);
+    }
+
+    pub fn day_length(&self) -> f32 {
+        self.day_length
+    }
+
+    pub fn set_day_length(&mut self, day_length: f32) {
+        self.day_length = day_length;
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        if self.day_length > 0.0 {
+            self.time_of_day = wrap(self.time_of_day + delta / self.day_length);
+        }
+    }
+
+    /// The normalized direction towards the sun. The sun rises in +X and sets in -X, passing
+    /// slightly south of straight overhead at noon.
+    pub fn sun_direction(&self) -> Vector3<f32> {
+        let angle = Rad::full_turn() * (self.time_of_day - 0.25);
+        let tilt = Rad::full_turn() * 0.05;
+        let (sin, cos) = angle.sin_cos();
+
+        Vector3::new(cos, sin * tilt.cos(), sin * tilt.sin()).normalize()
+    }
+
+    /// The color of the sky straight up.
+    pub fn sky_color(&self) -> Vector3<f32> {
+        self.sky.sample(self.time_of_day)
+    }
+
+    /// The color of the sky at the horizon, distant fog fades to this color.
+    pub fn horizon_color(&self) -> Vector3<f32> {
+        self.horizon.sample(self.time_of_day)
+    }
+
+    /// Updates the sun and ambient light to match the time of day.
+    pub fn apply(&self, lighting: &mut Lighting) {
+        lighting.sun.direction = -self.sun_direction();
+        lighting.sun.color = self.sun.sample(self.time_of_day);
+        lighting.ambient = self.ambient.sample(self.time_of_day);
+    }
+}
+
+fn wrap(value: f32) -> f32 {
+    let wrapped = value % 1.0;
+    if wrapped < 0.0 { wrapped + 1.0 } else { wrapped }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::Vector3;
+    use daycycle::{Gradient, DayCycle};
+
+    #[test]
+    fn gradient_interpolates_and_wraps() {
+        let gradient = Gradient::new(vec![
+            (0.75, Vector3::new(0.0, 0.0, 1.0)),
+            (0.25, Vector3::new(1.0, 0.0, 0.0)),
+        ]);
+
+        assert_eq!(gradient.sample(0.25), Vector3::new(1.0, 0.0, 0.0));
+        assert_eq!(gradient.sample(0.5), Vector3::new(0.5, 0.0, 0.5));
+
+        // Midnight is halfway between the last key and the first key of the next day
+        assert_eq!(gradient.sample(0.0), Vector3::new(0.5, 0.0, 0.5));
+        assert_eq!(gradient.sample(1.0), gradient.sample(0.0));
+    }
+
+    #[test]
+    fn sun_is_up_at_noon_and_down_at_midnight() {
+        let mut cycle = DayCycle::new(60.0);
+
+        cycle.set_time_of_day(0.5);
+        assert!(cycle.sun_direction().y > 0.9);
+
+        cycle.set_time_of_day(0.0);
+        assert!(cycle.sun_direction().y < -0.9);
+    }
+
+    #[test]
+    fn update_advances_and_wraps_time() {
+        let mut cycle = DayCycle::new(100.0);
+        cycle.set_time_of_day(0.9);
+
+        cycle.update(20.0);
+
+        assert!((cycle.time_of_day() - 0.1).abs() < 0.0001);
+    }
+
+    #[test]
+    fn night_is_darker_than_day() {
+        let mut cycle = DayCycle::new(60.0);
+
+        cycle.set_time_of_day(0.0);
+        let night = cycle.sky_color();
+        cycle.set_time_of_day(0.5);
+        let day = cycle.sky_color();
+
+        assert!(night.x + night.y + night.z < day.x + day.y + day.z);
+    }
+}
@@ -0,0 +1,192 @@
+use cgmath::{Vector3, Matrix4, Rad, Angle, InnerSpace};
+
+/// Segments used to draw a circle of a sphere.
+const CIRCLE_SEGMENTS: usize = 24;
+
+#[derive(Copy, Clone, Debug)]
+pub struct DebugLine {
+    pub start: Vector3<f32>,
+    pub end: Vector3<f32>,
+    pub color: Vector3<f32>,
+    /// Seconds left before the line is removed, lines with 0.0 are drawn for a single frame.
+    pub lifetime: f32,
+}
+
+/// Immediate-mode debug shapes, drawn by the frontend as an overlay on top of the world.
+///
+/// Disabled until enabled through `set_enabled`. Only available in debug builds, in release builds
+/// all calls do nothing and are optimized out.
+pub struct DebugDraw {
+    enabled: bool,
+    lines: Vec<DebugLine>,
+}
+
+impl DebugDraw {
+    pub fn new() -> Self {
+        DebugDraw {
+            enabled: false,
+            lines: Vec::new(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        cfg!(debug_assertions) && self.enabled
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.lines.clear();
+        }
+    }
+
+    /// Ages the shapes and removes expired ones, called once every frame before drawing new
+    /// shapes.
+    pub fn update(&mut self, delta: f32) {
+        // Expiring before the frame is drawn, so a shape isn't drawn one frame longer than asked
+        for line in &mut self.lines {
+            line.lifetime -= delta;
+        }
+        self.lines.retain(|l| l.lifetime > 0.0);
+    }
+
+    pub fn lines(&self) -> &[DebugLine] {
+        &self.lines
+    }
+
+    pub fn line(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>, lifetime: f32) {
+        if !self.enabled() {
+            return;
+        }
+
+        self.lines.push(DebugLine {
+            start: start,
+            end: end,
+            color: color,
+            lifetime: lifetime,
+        });
+    }
+
+    /// Draws an axis-aligned box between the minimum and maximum corners.
+    pub fn aabb(&mut self, min: Vector3<f32>, max: Vector3<f32>, color: Vector3<f32>, lifetime: f32) {
+        if !self.enabled() {
+            return;
+        }
+
+        let corner = |i: usize| Vector3::new(
+            if i & 1 == 0 { min.x } else { max.x },
+            if i & 2 == 0 { min.y } else { max.y },
+            if i & 4 == 0 { min.z } else { max.z },
+        );
+
+        // Connect every corner to the corners that differ from it on a single axis
+        for i in 0..8 {
+            for &axis in &[1, 2, 4] {
+                if i & axis == 0 {
+                    self.line(corner(i), corner(i | axis), color, lifetime);
+                }
+            }
+        }
+    }
+
+    /// Draws a sphere as three circles, one around every axis.
+    pub fn sphere(&mut self, center: Vector3<f32>, radius: f32, color: Vector3<f32>, lifetime: f32) {
+        if !self.enabled() {
+            return;
+        }
+
+        let point = |i: usize, axis: usize| {
+            let (sin, cos) = (Rad::full_turn() * (i as f32 / CIRCLE_SEGMENTS as f32)).sin_cos();
+            let offset = match axis {
+                0 => Vector3::new(0.0, sin, cos),
+                1 => Vector3::new(sin, 0.0, cos),
+                _ => Vector3::new(sin, cos, 0.0),
+            };
+            center + offset * radius
+        };
+
+        for axis in 0..3 {
+            for i in 0..CIRCLE_SEGMENTS {
+                self.line(point(i, axis), point(i + 1, axis), color, lifetime);
+            }
+        }
+    }
+
+    /// Draws a line with an arrowhead at the end.
+    pub fn arrow(&mut self, start: Vector3<f32>, end: Vector3<f32>, color: Vector3<f32>, lifetime: f32) {
+        if !self.enabled() {
+            return;
+        }
+
+        self.line(start, end, color, lifetime);
+
+        let direction = end - start;
+        let length = direction.magnitude();
+        if length <= 0.0 {
+            return;
+        }
+
+        // Build the arrowhead from two vectors perpendicular to the line
+        let direction = direction / length;
+        let helper = if direction.y.abs() < 0.99 { Vector3::unit_y() } else { Vector3::unit_x() };
+        let side = direction.cross(helper).normalize();
+        let up = side.cross(direction);
+
+        let head = length * 0.2;
+        let base = end - direction * head;
+        for &offset in &[side, -side, up, -up] {
+            self.line(end, base + offset * head * 0.5, color, lifetime);
+        }
+    }
+
+    /// Draws the X, Y and Z axes of a transform in red, green and blue.
+    pub fn axes(&mut self, transform: &Matrix4<f32>, size: f32, lifetime: f32) {
+        if !self.enabled() {
+            return;
+        }
+
+        let origin = transform.w.truncate();
+        self.line(origin, origin + transform.x.truncate().normalize() * size, Vector3::new(1.0, 0.0, 0.0), lifetime);
+        self.line(origin, origin + transform.y.truncate().normalize() * size, Vector3::new(0.0, 1.0, 0.0), lifetime);
+        self.line(origin, origin + transform.z.truncate().normalize() * size, Vector3::new(0.0, 0.0, 1.0), lifetime);
+    }
+}
+
+// The shapes are only recorded in debug builds
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use cgmath::Vector3;
+    use debugdraw::DebugDraw;
+
+    #[test]
+    fn lines_expire_after_lifetime() {
+        let mut debug = DebugDraw::new();
+        debug.set_enabled(true);
+        let white = Vector3::new(1.0, 1.0, 1.0);
+
+        debug.line(Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 0.0, 0.0), white, 0.0);
+        debug.line(Vector3::new(0.0, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), white, 1.0);
+        assert_eq!(debug.lines().len(), 2);
+
+        // Single frame lines are drawn once and removed on the next update, the other one is
+        // gone once its second has passed
+        debug.update(0.6);
+        assert_eq!(debug.lines().len(), 1);
+        debug.update(0.6);
+        assert_eq!(debug.lines().len(), 0);
+    }
+
+    #[test]
+    fn aabb_has_twelve_edges() {
+        let mut debug = DebugDraw::new();
+        debug.set_enabled(true);
+        debug.aabb(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0), Vector3::new(1.0, 1.0, 1.0), 0.0);
+
+        assert_eq!(debug.lines().len(), 12);
+        for line in debug.lines() {
+            // Every edge runs along a single axis of the 2 unit wide box
+            let d = line.end - line.start;
+            assert_eq!(d.x.abs() + d.y.abs() + d.z.abs(), 2.0);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use cgmath::{Vector3, Matrix4, Quaternion, One};
+
+/// The models the frontend knows how to render.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Model {
+    /// The Utah teapot, 3.15 high with its bottom at the origin.
+    Teapot,
+    /// A cube with sides of 1.0, centered on the origin.
+    Cube,
+    /// A sphere with a diameter of 1.0, centered on the origin.
+    Sphere,
+    /// A 1.0 by 1.0 square facing up, centered on the origin.
+    Plane,
+    /// A capsule along the Y axis, 1.0 high and 0.5 wide, centered on the origin.
+    Capsule,
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct Transform {
+    pub position: Vector3<f32>,
+    pub rotation: Quaternion<f32>,
+    pub scale: f32,
+}
+
+impl Transform {
+    pub fn new() -> Self {
+        Transform {
+            position: Vector3::new(0.0, 0.0, 0.0),
+            rotation: Quaternion::one(),
+            scale: 1.0,
+        }
+    }
+
+    /// Calculates the model matrix, applying scale first, then rotation, then translation.
+    pub fn matrix(&self) -> Matrix4<f32> {
+        let translation = Matrix4::from_translation(self.position);
+        let rotation = Matrix4::from(self.rotation);
+        let scale = Matrix4::from_scale(self.scale);
+        translation * rotation * scale
+    }
+}
+
+pub struct Entity {
+    model: Model,
+    material: String,
+    transform: Transform,
+}
+
+impl Entity {
+    pub fn new<S: Into<String>>(model: Model, material: S, transform: Transform) -> Self {
+        Entity {
+            model: model,
+            material: material.into(),
+            transform: transform,
+        }
+    }
+
+    pub fn model(&self) -> Model {
+        self.model
+    }
+
+    /// The name of the material to draw the model with, as defined in the materials file.
+    pub fn material(&self) -> &str {
+        &self.material
+    }
+
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector3, Vector4, Quaternion, Rad, Rotation3, Angle};
+    use entity::Transform;
+
+    #[test]
+    fn matrix_scales_rotates_then_translates() {
+        let mut transform = Transform::new();
+        transform.position = Vector3::new(10.0, 0.0, 0.0);
+        transform.rotation = Quaternion::from_angle_y(Rad::full_turn() * 0.25);
+        transform.scale = 2.0;
+
+        let result = transform.matrix() * Vector4::new(1.0, 0.0, 0.0, 1.0);
+
+        // (1, 0, 0) scaled to (2, 0, 0), rotated a quarter turn around Y to (0, 0, -2)
+        assert!((result.x - 10.0).abs() < 0.0001);
+        assert!(result.y.abs() < 0.0001);
+        assert!((result.z + 2.0).abs() < 0.0001);
+    }
+}
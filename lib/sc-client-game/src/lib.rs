@@ -1,44 +1,164 @@
 extern crate sc_input_data;
 extern crate cgmath;
 
+mod atmosphere;
 mod camera;
+mod context;
+mod daycycle;
+mod debugdraw;
+mod entity;
+mod lighting;
 mod world;
 
 use std::collections::VecDeque;
 use cgmath::Vector2;
-use sc_input_data::{Button, InputState};
+use sc_input_data::{Button, CursorMode, InputState, TextInput};
 
+pub use atmosphere::Atmosphere;
 pub use camera::Camera;
+pub use context::{Action, ActionMap, Binding, InputContext, InputContexts};
+pub use daycycle::{DayCycle, Gradient};
+pub use debugdraw::{DebugDraw, DebugLine};
+pub use entity::{Entity, Model, Transform};
+pub use lighting::{Lighting, DirectionalLight, PointLight};
 pub use world::ClientWorld;
 
 pub struct ClientGame {
     input: InputState,
+    contexts: InputContexts,
     world: ClientWorld,
+    pause_unfocused: bool,
+    paused: bool,
 
     commands: VecDeque<ClientGameCommand>,
 }
 
 impl ClientGame {
     pub fn connect() -> Self {
-        ClientGame {
+        let mut game = ClientGame {
             input: InputState::new(),
+            contexts: InputContexts::new(),
             world: ClientWorld::new(),
+            pause_unfocused: false,
+            paused: false,
 
             commands: VecDeque::new(),
-        }
+        };
+
+        // Sets up the cursor for gameplay
+        game.context_changed();
+        game
     }
 
     pub fn world(&self) -> &ClientWorld {
         &self.world
     }
 
+    pub fn world_mut(&mut self) -> &mut ClientWorld {
+        &mut self.world
+    }
+
     pub fn handle_event(&mut self, event: ClientGameEvent) {
         match event {
-            ClientGameEvent::ButtonState(button, state) =>
-                self.input.set(button, state),
-            ClientGameEvent::MouseMove(position, should_track) =>
-                self.input.set_mouse(position, should_track),
+            ClientGameEvent::ButtonState(button, state) => match self.contexts.resolve(button) {
+                Some(Action::Hold(held)) => self.input.set(held, state),
+                Some(action) => if state { self.trigger(action) },
+                None => (),
+            },
+            // The position is kept up to date either way, only motion depends on the contexts
+            ClientGameEvent::MouseMove(position, should_track) => {
+                let track = should_track && self.contexts.pointer_reaches_gameplay();
+                self.input.set_mouse(position, track);
+            },
+            ClientGameEvent::Scroll(lines) => if self.contexts.pointer_reaches_gameplay() {
+                self.input.add_scroll(lines);
+            },
+            ClientGameEvent::Text(text) =>
+                self.input.add_text(text),
+            ClientGameEvent::Focus(focused) => {
+                // Releases for anything held while switching away won't reach us
+                if !focused {
+                    self.input.release_all();
+                }
+                self.paused = !focused && self.pause_unfocused;
+            },
+        }
+    }
+
+    pub fn input_context(&self) -> InputContext {
+        self.contexts.top()
+    }
+
+    /// The bindings of a context, for changing them from the settings.
+    pub fn action_map_mut(&mut self, context: InputContext) -> &mut ActionMap {
+        self.contexts.map_mut(context)
+    }
+
+    /// Opens the context on top, gameplay is always open and can't be opened again.
+    pub fn open_context(&mut self, context: InputContext) {
+        if self.contexts.push(context) {
+            self.context_changed();
+        }
+    }
+
+    /// Closes the top context, gameplay itself is never closed.
+    pub fn close_context(&mut self) {
+        if self.contexts.pop() {
+            self.context_changed();
+        }
+    }
+
+    fn trigger(&mut self, action: Action) {
+        match action {
+            Action::Hold(button) => self.input.set(button, true),
+            Action::Open(context) => self.open_context(context),
+            Action::Close => self.close_context(),
+            Action::Submit => {
+                self.input.add_text(TextInput::Enter);
+                self.close_context();
+            },
+            Action::Quit => self.quit(),
+        }
+    }
+
+    /// Switches the frontend over to how the new top context takes input.
+    fn context_changed(&mut self) {
+        // Releases of held buttons go to the new context, which won't pass them down
+        self.input.release_all();
+
+        let top = self.contexts.top();
+        self.set_cursor_mode(top.cursor_mode());
+        self.set_text_input(top.takes_text());
+    }
+
+    /// Stops updating the local world while the window doesn't have focus. Once there's a
+    /// server it keeps running regardless.
+    pub fn set_pause_unfocused(&mut self, pause: bool) {
+        self.pause_unfocused = pause;
+    }
+
+    /// Asks the frontend to save the next frame it draws as a screenshot.
+    pub fn request_screenshot(&mut self) {
+        self.commands.push_back(ClientGameCommand::Screenshot);
+    }
+
+    /// Changes how the cursor behaves, a menu would free it and grab it again when closed.
+    pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+        self.commands.push_back(ClientGameCommand::SetCursorMode(mode));
+    }
+
+    /// Starts or stops sending typed text instead of buttons, for chat and the console.
+    pub fn set_text_input(&mut self, enabled: bool) {
+        // Keys held when the field opens would otherwise stay down until it closes
+        if enabled {
+            self.input.release_all();
         }
+        self.commands.push_back(ClientGameCommand::SetTextInput(enabled));
+    }
+
+    /// Ends the session, the pause menu does this on Confirm.
+    pub fn quit(&mut self) {
+        self.commands.push_back(ClientGameCommand::Quit);
     }
 
     pub fn next_command(&mut self) -> Option<ClientGameCommand> {
@@ -46,17 +166,32 @@ impl ClientGame {
     }
 
     pub fn update(&mut self, delta: f32) {
-        // Update the world
-        self.world.update(delta, &self.input);
+        // Update the world, the pause menu stops it as well
+        if !self.paused && !self.contexts.top().pauses() {
+            self.world.update(delta, &self.input);
+        }
 
         self.input.end_frame();
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum ClientGameEvent {
     ButtonState(Button, bool),
     MouseMove(Vector2<i32>, bool),
+    Scroll(Vector2<f32>),
+    Text(TextInput),
+    /// The window gained or lost focus.
+    Focus(bool),
 }
 
 pub enum ClientGameCommand {
+    /// Save the next rendered frame as a screenshot.
+    Screenshot,
+    /// Switch the cursor to a different mode.
+    SetCursorMode(CursorMode),
+    /// Send text input events rather than gameplay buttons.
+    SetTextInput(bool),
+    /// Close the game.
+    Quit,
 }
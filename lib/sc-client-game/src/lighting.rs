@@ -0,0 +1,75 @@
+use cgmath::{Vector3, Rad, Angle, InnerSpace};
+
+/// A light infinitely far away, shining in the same direction everywhere.
+#[derive(Copy, Clone, Debug)]
+pub struct DirectionalLight {
+    /// The direction the light is travelling in, pointing away from the light.
+    pub direction: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+}
+
+impl DirectionalLight {
+    /// Creates a light shining from the given angles. Azimuth is measured from -Z towards +X,
+    /// elevation from the horizon upwards.
+    pub fn from_angles(azimuth: Rad<f32>, elevation: Rad<f32>, color: Vector3<f32>, intensity: f32)
+                       -> Self {
+        let (az_sin, az_cos) = azimuth.sin_cos();
+        let (el_sin, el_cos) = elevation.sin_cos();
+        let towards_light = Vector3::new(az_sin * el_cos, el_sin, -az_cos * el_cos);
+
+        DirectionalLight {
+            direction: -towards_light.normalize(),
+            color: color,
+            intensity: intensity,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: Vector3<f32>,
+    pub color: Vector3<f32>,
+    pub intensity: f32,
+    /// The distance at which the light's contribution falls off to zero.
+    pub range: f32,
+}
+
+/// All lights affecting the world.
+#[derive(Clone, Debug)]
+pub struct Lighting {
+    pub sun: DirectionalLight,
+    pub ambient: Vector3<f32>,
+    pub point_lights: Vec<PointLight>,
+}
+
+impl Lighting {
+    pub fn new() -> Self {
+        Lighting {
+            sun: DirectionalLight::from_angles(
+                Rad::full_turn() * 0.125, Rad::full_turn() * 0.125,
+                Vector3::new(1.0, 0.95, 0.85), 1.0
+            ),
+            ambient: Vector3::new(0.15, 0.15, 0.2),
+            point_lights: Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector3, Rad, Angle};
+    use lighting::DirectionalLight;
+
+    #[test]
+    fn light_from_angles_points_away_from_sky() {
+        let white = Vector3::new(1.0, 1.0, 1.0);
+
+        let overhead = DirectionalLight::from_angles(Rad(0.0), Rad::full_turn() * 0.25, white, 1.0);
+        assert!((overhead.direction.y + 1.0).abs() < 0.0001);
+
+        let horizon = DirectionalLight::from_angles(Rad::full_turn() * 0.25, Rad(0.0), white, 1.0);
+        assert!((horizon.direction.x + 1.0).abs() < 0.0001);
+        assert!(horizon.direction.y.abs() < 0.0001);
+    }
+}
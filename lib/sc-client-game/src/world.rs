@@ -1,22 +1,116 @@
+use cgmath::{Vector3, Quaternion, Rad, Rotation3, Angle};
 use sc_input_data::InputState;
+use atmosphere::Atmosphere;
 use camera::Camera;
+use daycycle::DayCycle;
+use debugdraw::DebugDraw;
+use entity::{Entity, Model, Transform};
+use lighting::{Lighting, PointLight};
 
 pub struct ClientWorld {
     camera: Camera,
+    entities: Vec<Entity>,
+    lighting: Lighting,
+    day_cycle: DayCycle,
+    atmosphere: Atmosphere,
+    debug: DebugDraw,
 }
 
 impl ClientWorld {
     pub fn new() -> Self {
+        // Until we receive level data, place a row of teapots on a floor as a test scene
+        let materials = ["red_plastic", "checker", "gold", "rubber", "default"];
+        let mut entities: Vec<_> = materials.iter().enumerate().map(|(i, material)| {
+            let mut transform = Transform::new();
+            transform.position = Vector3::new(i as f32 - 2.0, -0.4, 0.0);
+            transform.rotation = Quaternion::from_angle_y(Rad::full_turn() * (i as f32 * 0.125));
+            transform.scale = 0.28;
+            Entity::new(Model::Teapot, *material, transform)
+        }).collect();
+
+        let mut floor = Transform::new();
+        floor.position = Vector3::new(0.0, -0.4, 0.0);
+        floor.scale = 12.0;
+        entities.push(Entity::new(Model::Plane, "default", floor));
+
+        let mut lighting = Lighting::new();
+        lighting.point_lights.push(PointLight {
+            position: Vector3::new(-1.5, 0.5, 0.5),
+            color: Vector3::new(0.2, 0.4, 1.0),
+            intensity: 2.0,
+            range: 2.0,
+        });
+        lighting.point_lights.push(PointLight {
+            position: Vector3::new(1.5, 0.5, 0.5),
+            color: Vector3::new(1.0, 0.5, 0.1),
+            intensity: 2.0,
+            range: 2.0,
+        });
+
         ClientWorld {
             camera: Camera::new(),
+            entities: entities,
+            lighting: lighting,
+            day_cycle: DayCycle::new(20.0 * 60.0),
+            atmosphere: Atmosphere::new(),
+            debug: DebugDraw::new(),
         }
     }
 
     pub fn update(&mut self, delta: f32, input: &InputState) {
+        self.debug.update(delta);
+
         self.camera.update(delta, input);
+
+        self.day_cycle.update(delta);
+        self.day_cycle.apply(&mut self.lighting);
+
+        // Show where the lights are and how far they reach
+        for light in &self.lighting.point_lights {
+            self.debug.sphere(light.position, light.range, light.color, 0.0);
+        }
+        for entity in &self.entities {
+            self.debug.axes(&entity.transform().matrix(), 0.25, 0.0);
+        }
     }
 
     pub fn camera(&self) -> &Camera {
         &self.camera
     }
+
+    pub fn entities(&self) -> &[Entity] {
+        &self.entities
+    }
+
+    pub fn lighting(&self) -> &Lighting {
+        &self.lighting
+    }
+
+    pub fn lighting_mut(&mut self) -> &mut Lighting {
+        &mut self.lighting
+    }
+
+    pub fn day_cycle(&self) -> &DayCycle {
+        &self.day_cycle
+    }
+
+    pub fn day_cycle_mut(&mut self) -> &mut DayCycle {
+        &mut self.day_cycle
+    }
+
+    pub fn atmosphere(&self) -> &Atmosphere {
+        &self.atmosphere
+    }
+
+    pub fn atmosphere_mut(&mut self) -> &mut Atmosphere {
+        &mut self.atmosphere
+    }
+
+    pub fn debug(&self) -> &DebugDraw {
+        &self.debug
+    }
+
+    pub fn debug_mut(&mut self) -> &mut DebugDraw {
+        &mut self.debug
+    }
 }
@@ -1,9 +1,43 @@
+extern crate glsl_to_spirv;
 extern crate vulkano_shaders;
 
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+
 fn main() {
-    // building the shaders used in the examples
-    vulkano_shaders::build_glsl_shaders([
+    let shaders = [
         ("src/shader_vs.glsl", vulkano_shaders::ShaderType::Vertex),
         ("src/shader_fs.glsl", vulkano_shaders::ShaderType::Fragment),
-    ].iter().cloned());
+        ("src/shader_shadow_vs.glsl", vulkano_shaders::ShaderType::Vertex),
+        ("src/shader_shadow_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_sky_vs.glsl", vulkano_shaders::ShaderType::Vertex),
+        ("src/shader_sky_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_debug_vs.glsl", vulkano_shaders::ShaderType::Vertex),
+        ("src/shader_debug_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_overlay_vs.glsl", vulkano_shaders::ShaderType::Vertex),
+        ("src/shader_overlay_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_post_vs.glsl", vulkano_shaders::ShaderType::Vertex),
+        ("src/shader_post_bright_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_post_blur_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_post_tonemap_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+        ("src/shader_post_fxaa_fs.glsl", vulkano_shaders::ShaderType::Fragment),
+    ];
+
+    // building the shaders used in the examples
+    vulkano_shaders::build_glsl_shaders(shaders.iter().cloned());
+
+    // Keep the SPIR-V as well, shaders reloaded at runtime are checked against its interface
+    let dest = env::var("OUT_DIR").unwrap();
+    for &(shader, ref ty) in &shaders {
+        let output = Path::new(&dest).join("spirv").join(shader);
+        fs::create_dir_all(output.parent().unwrap()).unwrap();
+
+        let mut source = String::new();
+        File::open(shader).and_then(|mut f| f.read_to_string(&mut source))
+            .expect("failed to read shader");
+        let mut spirv = glsl_to_spirv::compile(&source, ty.clone()).unwrap();
+        io::copy(&mut spirv, &mut File::create(output).unwrap()).unwrap();
+    }
 }
@@ -0,0 +1,201 @@
+use cgmath::{Matrix, Matrix4, Vector3, Vector4, InnerSpace};
+
+/// An axis-aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    /// Creates the smallest box containing all points, or None if there are no points.
+    pub fn from_points<I: IntoIterator<Item=Vector3<f32>>>(points: I) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = match points.next() {
+            Some(first) => first,
+            None => return None,
+        };
+
+        Some(points.fold(Aabb { min: first, max: first }, |aabb, p| Aabb {
+            min: Vector3::new(aabb.min.x.min(p.x), aabb.min.y.min(p.y), aabb.min.z.min(p.z)),
+            max: Vector3::new(aabb.max.x.max(p.x), aabb.max.y.max(p.y), aabb.max.z.max(p.z)),
+        }))
+    }
+
+    pub fn center(&self) -> Vector3<f32> {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn extents(&self) -> Vector3<f32> {
+        (self.max - self.min) * 0.5
+    }
+
+    /// Calculates the axis-aligned box containing this box after being transformed.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Aabb {
+        let center = (*matrix * self.center().extend(1.0)).truncate();
+        let extents = self.extents();
+
+        // Every axis of the new box is as far as the old extents reach along it after rotating
+        let reach = |row: usize| {
+            let row = matrix.row(row);
+            row.x.abs() * extents.x + row.y.abs() * extents.y + row.z.abs() * extents.z
+        };
+        let extents = Vector3::new(reach(0), reach(1), reach(2));
+
+        Aabb {
+            min: center - extents,
+            max: center + extents,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BoundingSphere {
+    pub center: Vector3<f32>,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    /// Creates a sphere around the box's center containing all points.
+    pub fn from_points<I: IntoIterator<Item=Vector3<f32>>>(aabb: &Aabb, points: I) -> Self {
+        let center = aabb.center();
+        let radius = points.into_iter().map(|p| (p - center).magnitude()).fold(0.0, f32::max);
+
+        BoundingSphere {
+            center: center,
+            radius: radius,
+        }
+    }
+
+    /// Calculates a sphere containing this sphere after being transformed.
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> BoundingSphere {
+        // Non-uniform scale stretches the sphere, so the largest scale has to cover all axes
+        let scale = matrix.x.truncate().magnitude()
+            .max(matrix.y.truncate().magnitude())
+            .max(matrix.z.truncate().magnitude());
+
+        BoundingSphere {
+            center: (*matrix * self.center.extend(1.0)).truncate(),
+            radius: self.radius * scale,
+        }
+    }
+}
+
+/// The bounding volumes of a mesh, in the mesh's model space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Bounds {
+    pub aabb: Aabb,
+    pub sphere: BoundingSphere,
+}
+
+impl Bounds {
+    pub fn from_points(points: &[Vector3<f32>]) -> Option<Self> {
+        Aabb::from_points(points.iter().cloned()).map(|aabb| Bounds {
+            aabb: aabb,
+            sphere: BoundingSphere::from_points(&aabb, points.iter().cloned()),
+        })
+    }
+}
+
+/// The six planes enclosing the volume visible through a view-projection matrix.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    /// Plane normals point inwards, with the distance in w.
+    planes: [Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix.
+    ///
+    /// The near plane is extracted for OpenGL's -1.0 to 1.0 depth range, which also includes
+    /// everything visible in Vulkan's 0.0 to 1.0 range, so this is safe to use with either.
+    pub fn from_matrix(view_proj: &Matrix4<f32>) -> Self {
+        let (x, y, z, w) = (view_proj.row(0), view_proj.row(1), view_proj.row(2), view_proj.row(3));
+        let planes = [w + x, w - x, w + y, w - y, w + z, w - z];
+
+        let mut normalized = [Vector4::new(0.0, 0.0, 0.0, 0.0); 6];
+        for (n, p) in normalized.iter_mut().zip(planes.iter()) {
+            *n = *p / p.truncate().magnitude();
+        }
+
+        Frustum {
+            planes: normalized,
+        }
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes.iter().all(|p| p.truncate().dot(sphere.center) + p.w >= -sphere.radius)
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        // Only the corner furthest along the plane's normal has to be checked
+        self.planes.iter().all(|p| {
+            let corner = Vector3::new(
+                if p.x >= 0.0 { aabb.max.x } else { aabb.min.x },
+                if p.y >= 0.0 { aabb.max.y } else { aabb.min.y },
+                if p.z >= 0.0 { aabb.max.z } else { aabb.min.z },
+            );
+            p.truncate().dot(corner) + p.w >= 0.0
+        })
+    }
+
+    /// Checks if a mesh with the bounds is at least partially inside, when placed with the model
+    /// matrix. The cheap sphere check is done first, only passing spheres check the box.
+    pub fn intersects(&self, bounds: &Bounds, model: &Matrix4<f32>) -> bool {
+        self.intersects_sphere(&bounds.sphere.transform(model)) &&
+            self.intersects_aabb(&bounds.aabb.transform(model))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{self, Matrix4, Vector3, Point3, Deg};
+    use bounds::{Aabb, Bounds, Frustum};
+
+    fn camera_frustum() -> Frustum {
+        // Looking down -Z from the origin
+        let proj = cgmath::perspective(Deg(90.0), 1.0, 0.1, 100.0);
+        let view = Matrix4::look_at(
+            Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 0.0, -1.0), Vector3::unit_y()
+        );
+        Frustum::from_matrix(&(proj * view))
+    }
+
+    fn unit_cube() -> Bounds {
+        Bounds::from_points(&[Vector3::new(-1.0, -1.0, -1.0), Vector3::new(1.0, 1.0, 1.0)]).unwrap()
+    }
+
+    #[test]
+    fn transformed_aabb_contains_rotated_box() {
+        let aabb = unit_cube().aabb;
+        let rotated = aabb.transform(&(Matrix4::from_translation(Vector3::new(5.0, 0.0, 0.0)) *
+            Matrix4::from_angle_y(Deg(45.0))));
+
+        // A quarter turn keeps the box the same, an eighth turn makes it wider by sqrt(2)
+        let reach = 2.0f32.sqrt();
+        assert!((rotated.max.x - (5.0 + reach)).abs() < 0.0001);
+        assert!((rotated.min.z + reach).abs() < 0.0001);
+        assert!((rotated.max.y - 1.0).abs() < 0.0001);
+    }
+
+    #[test]
+    fn culls_meshes_outside_frustum() {
+        let frustum = camera_frustum();
+        let bounds = unit_cube();
+        let at = |x, y, z| Matrix4::from_translation(Vector3::new(x, y, z));
+
+        assert!(frustum.intersects(&bounds, &at(0.0, 0.0, -10.0)));
+        // Partially inside the left edge
+        assert!(frustum.intersects(&bounds, &at(-10.5, 0.0, -10.0)));
+        // Behind, beyond the far plane, and to the side
+        assert!(!frustum.intersects(&bounds, &at(0.0, 0.0, 10.0)));
+        assert!(!frustum.intersects(&bounds, &at(0.0, 0.0, -200.0)));
+        assert!(!frustum.intersects(&bounds, &at(20.0, 0.0, -10.0)));
+    }
+
+    #[test]
+    fn empty_points_have_no_bounds() {
+        assert_eq!(Aabb::from_points(Vec::new()), None);
+        assert_eq!(Bounds::from_points(&[]), None);
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+use std::str::FromStr;
+
+/// Key-value configuration loaded from a simple ini-style file.
+///
+/// Keys are stored with their section as prefix, so `width` in the `[graphics]` section can be
+/// looked up as `graphics.width`.
+pub struct Config {
+    values: HashMap<String, String>,
+}
+
+impl Config {
+    pub fn new() -> Self {
+        Config {
+            values: HashMap::new(),
+        }
+    }
+
+    /// Loads the config file at the given path, a missing file results in an empty config.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(Config::new()),
+            Err(e) => return Err(ConfigError::Io(e)),
+        };
+
+        let mut source = String::new();
+        try!(file.read_to_string(&mut source).map_err(ConfigError::Io));
+
+        Config::parse(&source)
+    }
+
+    pub fn parse(source: &str) -> Result<Self, ConfigError> {
+        let mut config = Config::new();
+        let mut section = String::new();
+
+        for (i, line) in source.lines().enumerate() {
+            // Strip comments and surrounding whitespace, skipping lines with nothing left
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() { continue; }
+
+            // Section headers change the prefix of all keys following it
+            if line.starts_with('[') && line.ends_with(']') {
+                section = line[1..line.len()-1].trim().to_string();
+                continue;
+            }
+
+            // Anything else has to be a key-value pair
+            let mut parts = line.splitn(2, '=');
+            let key = parts.next().unwrap().trim();
+            let value = try!(parts.next().ok_or(ConfigError::Syntax(i + 1))).trim();
+            if key.is_empty() {
+                return Err(ConfigError::Syntax(i + 1));
+            }
+
+            let key = if section.is_empty() { key.to_string() } else { format!("{}.{}", section, key) };
+            config.values.insert(key, value.to_string());
+        }
+
+        Ok(config)
+    }
+
+    pub fn set<T: ToString>(&mut self, key: &str, value: T) {
+        self.values.insert(key.to_string(), value.to_string());
+    }
+
+    /// Overrides values with `--section.key=value` arguments, returning all other arguments.
+    pub fn apply_args<I: IntoIterator<Item=String>>(&mut self, args: I) -> Vec<String> {
+        let mut remaining = Vec::new();
+
+        for arg in args {
+            if arg.starts_with("--") && arg.contains('=') {
+                let mut parts = arg[2..].splitn(2, '=');
+                let key = parts.next().unwrap();
+                let value = parts.next().unwrap();
+                self.set(key, value);
+            } else {
+                remaining.push(arg);
+            }
+        }
+
+        remaining
+    }
+
+    pub fn get<T: FromStr>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        match self.values.get(key) {
+            Some(value) => value.parse()
+                .map(Some)
+                .map_err(|_| ConfigError::InvalidValue(key.to_string(), value.clone())),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_or<T: FromStr>(&self, key: &str, default: T) -> Result<T, ConfigError> {
+        self.get(key).map(|v| v.unwrap_or(default))
+    }
+
+    /// Lists the names of all sections containing at least one key, in sorted order.
+    pub fn sections(&self) -> Vec<String> {
+        let mut sections: Vec<_> = self.values.keys()
+            .filter_map(|k| k.rfind('.').map(|i| k[..i].to_string()))
+            .collect();
+        sections.sort();
+        sections.dedup();
+        sections
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Syntax(usize), // line
+    InvalidValue(String, String), // key, value
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref e) => write!(f, "unable to read config: {}", e),
+            ConfigError::Syntax(line) => write!(f, "invalid syntax on config line {}", line),
+            ConfigError::InvalidValue(ref key, ref value) =>
+                write!(f, "invalid value \"{}\" for config key \"{}\"", value, key),
+        }
+    }
+}
+
+impl Error for ConfigError {
+    fn description(&self) -> &str {
+        match *self {
+            ConfigError::Io(_) => "unable to read config",
+            ConfigError::Syntax(_) => "invalid config syntax",
+            ConfigError::InvalidValue(_, _) => "invalid config value",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use config::{Config, ConfigError};
+
+    #[test]
+    fn parses_sections_into_prefixed_keys() {
+        let config = Config::parse("top = 1\n[graphics]\nwidth = 800 # comment\n").unwrap();
+
+        assert_eq!(config.get::<i32>("top").unwrap(), Some(1));
+        assert_eq!(config.get::<u32>("graphics.width").unwrap(), Some(800));
+        assert_eq!(config.get::<u32>("width").unwrap(), None);
+    }
+
+    #[test]
+    fn rejects_lines_without_values() {
+        match Config::parse("[graphics]\nwidth\n") {
+            Err(ConfigError::Syntax(2)) => (),
+            _ => panic!("expected syntax error on line 2"),
+        }
+    }
+
+    #[test]
+    fn args_override_values() {
+        let mut config = Config::parse("[device]\nselect = auto\n").unwrap();
+        let remaining = config.apply_args(vec![
+            "--device.select=1".to_string(), "--list-devices".to_string()
+        ]);
+
+        assert_eq!(config.get::<usize>("device.select").unwrap(), Some(1));
+        assert_eq!(remaining, vec!["--list-devices".to_string()]);
+    }
+
+    #[test]
+    fn reports_unparsable_values() {
+        let config = Config::parse("fov = wide").unwrap();
+
+        assert!(config.get::<f32>("fov").is_err());
+        assert_eq!(config.get_or("missing", 90.0).unwrap(), 90.0);
+    }
+
+    #[test]
+    fn lists_sections() {
+        let config = Config::parse("top = 1\n[gold]\ncolor = 1\nspecular = 1\n[brick]\nroughness = 1\n")
+            .unwrap();
+
+        assert_eq!(config.sections(), vec!["brick".to_string(), "gold".to_string()]);
+    }
+}
@@ -0,0 +1,94 @@
+use winit::CursorState;
+
+use sc_input_data::CursorMode;
+
+/// Tracks the cursor mode the game asked for against what the window can have right now.
+///
+/// A grab is let go when the window loses focus and only taken back once the player clicks into
+/// the window again, so alt-tabbing out doesn't fight them for the cursor. Winit doesn't give us
+/// raw mouse motion, relative motion comes from moving the grabbed cursor back to the center.
+pub struct CursorTracker {
+    mode: CursorMode,
+    focused: bool,
+    suspended: bool,
+}
+
+impl CursorTracker {
+    pub fn new(mode: CursorMode) -> Self {
+        CursorTracker {
+            mode: mode,
+            focused: true,
+            suspended: false,
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: CursorMode) {
+        self.mode = mode;
+
+        // Asking for a grab while unfocused waits for a click, same as after losing focus
+        self.suspended = mode == CursorMode::Grabbed && !self.focused;
+    }
+
+    pub fn set_focused(&mut self, focused: bool) {
+        self.focused = focused;
+        if !focused && self.mode == CursorMode::Grabbed {
+            self.suspended = true;
+        }
+    }
+
+    /// Handles a mouse button press in the window, returning true if it took the grab back and
+    /// shouldn't reach the game.
+    pub fn click(&mut self) -> bool {
+        if self.focused && self.suspended {
+            self.suspended = false;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// If mouse motion should be turned into relative motion by re-centering the cursor.
+    pub fn is_grabbed(&self) -> bool {
+        self.mode == CursorMode::Grabbed && self.focused && !self.suspended
+    }
+
+    pub fn window_state(&self) -> CursorState {
+        match self.mode {
+            CursorMode::Grabbed if self.is_grabbed() => CursorState::Grab,
+            CursorMode::Hidden if self.focused => CursorState::Hide,
+            _ => CursorState::Normal,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sc_input_data::CursorMode;
+    use cursor::CursorTracker;
+
+    #[test]
+    fn grab_waits_for_click_after_focus_loss() {
+        let mut cursor = CursorTracker::new(CursorMode::Grabbed);
+        assert!(cursor.is_grabbed());
+        assert!(!cursor.click());
+
+        cursor.set_focused(false);
+        assert!(!cursor.is_grabbed());
+
+        // Focus coming back alone doesn't take the cursor
+        cursor.set_focused(true);
+        assert!(!cursor.is_grabbed());
+        assert!(cursor.click());
+        assert!(cursor.is_grabbed());
+    }
+
+    #[test]
+    fn free_mode_ignores_clicks() {
+        let mut cursor = CursorTracker::new(CursorMode::Free);
+        cursor.set_focused(false);
+        cursor.set_focused(true);
+
+        assert!(!cursor.click());
+        assert!(!cursor.is_grabbed());
+    }
+}
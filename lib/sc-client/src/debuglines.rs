@@ -0,0 +1,97 @@
+use std::sync::Arc;
+
+use cgmath::Matrix4;
+use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
+use vulkano::command_buffer::{DynamicState, PrimaryCommandBufferBuilderInlineDraw};
+use vulkano::device::Device;
+use vulkano::framebuffer::Subpass;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineParams};
+use vulkano::pipeline::blend::Blend;
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::input_assembly::{InputAssembly, PrimitiveTopology};
+use vulkano::pipeline::multisample::Multisample;
+use vulkano::pipeline::raster::Rasterization;
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::viewport::ViewportsState;
+
+use sc_client_game::DebugLine;
+use error::FrontendError;
+use frontend::renderpass;
+use {debug_vs, debug_fs};
+
+mod pipeline_layout {
+    pipeline_layout!{
+        push_constants: {
+            view_proj: [[f32; 4]; 4]
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct DebugVertex {
+    i_position: [f32; 3],
+    i_color: [f32; 3],
+}
+
+impl_vertex!(DebugVertex, i_position, i_color);
+
+/// Turns debug lines into pairs of vertices, as drawn by the line list pipeline.
+pub fn vertices(lines: &[DebugLine]) -> Vec<DebugVertex> {
+    let mut vertices = Vec::with_capacity(lines.len() * 2);
+    for line in lines {
+        vertices.push(DebugVertex { i_position: line.start.into(), i_color: line.color.into() });
+        vertices.push(DebugVertex { i_position: line.end.into(), i_color: line.color.into() });
+    }
+    vertices
+}
+
+/// Draws debug lines as an overlay on top of the world.
+pub struct DebugPass {
+    pipeline: Arc<GraphicsPipeline<SingleBufferDefinition<DebugVertex>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>>,
+}
+
+impl DebugPass {
+    pub fn new(device: &Arc<Device>, renderpass: &Arc<renderpass::CustomRenderPass>)
+               -> Result<Self, FrontendError> {
+        let vs = try!(debug_vs::Shader::load(device).map_err(FrontendError::Shader));
+        let fs = try!(debug_fs::Shader::load(device).map_err(FrontendError::Shader));
+
+        let layout = try!(pipeline_layout::CustomPipeline::new(device)
+            .map_err(FrontendError::PipelineLayout));
+
+        let pipeline = try!(GraphicsPipeline::new(device, GraphicsPipelineParams {
+            vertex_input: SingleBufferDefinition::new(),
+            vertex_shader: vs.main_entry_point(),
+            input_assembly: InputAssembly {
+                topology: PrimitiveTopology::LineList,
+                primitive_restart_enable: false,
+            },
+            tessellation: None,
+            geometry_shader: None,
+            viewport: ViewportsState::Dynamic { num: 1 },
+            raster: Rasterization::default(),
+            multisample: Multisample::disabled(),
+            fragment_shader: fs.main_entry_point(),
+            // Debug lines should stay visible through geometry, so they ignore the depth buffer
+            depth_stencil: DepthStencil::disabled(),
+            blend: Blend::pass_through(),
+            layout: &layout,
+            render_pass: Subpass::from(renderpass, 0).unwrap(),
+        }).map_err(FrontendError::Pipeline));
+
+        Ok(DebugPass {
+            pipeline: pipeline,
+        })
+    }
+
+    /// Records drawing the lines in the buffer, this has to come after all other geometry.
+    pub fn draw(&self, builder: PrimaryCommandBufferBuilderInlineDraw, dynamic: &DynamicState,
+                vertices: &Arc<CpuAccessibleBuffer<[DebugVertex]>>, view_proj: &Matrix4<f32>)
+                -> PrimaryCommandBufferBuilderInlineDraw {
+        let constants = pipeline_layout::PushConstants {
+            view_proj: (*view_proj).into(),
+        };
+
+        builder.draw(&self.pipeline, vertices, dynamic, (), &constants)
+    }
+}
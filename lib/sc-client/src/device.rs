@@ -0,0 +1,229 @@
+use std::sync::Arc;
+
+use vulkano::instance::{self, Instance, InstanceExtensions, PhysicalDevice, PhysicalDeviceType};
+use vulkano::instance::debug::{DebugCallback, Message};
+use vulkano_win;
+
+use config::{Config, ConfigError};
+use error::FrontendError;
+
+const VALIDATION_LAYER: &'static str = "VK_LAYER_LUNARG_standard_validation";
+
+/// Device settings, read from the `[device]` section of the client config.
+#[derive(Clone, Debug, Default)]
+pub struct DeviceSettings {
+    pub selection: DeviceSelection,
+    pub validation: bool,
+}
+
+impl DeviceSettings {
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let selection = match try!(config.get::<String>("device.select")) {
+            Some(value) => DeviceSelection::parse(&value),
+            None => DeviceSelection::default(),
+        };
+
+        Ok(DeviceSettings {
+            selection: selection,
+            validation: try!(config.get_or("device.validation", false)),
+        })
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeviceSelection {
+    /// Prefer devices of this type, falling back to the most capable type available.
+    Type(PhysicalDeviceType),
+    Index(usize),
+    /// Case-insensitive part of the device's name.
+    Name(String),
+}
+
+impl DeviceSelection {
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "auto" | "discrete" => DeviceSelection::Type(PhysicalDeviceType::DiscreteGpu),
+            "integrated" => DeviceSelection::Type(PhysicalDeviceType::IntegratedGpu),
+            "virtual" => DeviceSelection::Type(PhysicalDeviceType::VirtualGpu),
+            "cpu" => DeviceSelection::Type(PhysicalDeviceType::Cpu),
+            _ => match value.parse() {
+                Ok(index) => DeviceSelection::Index(index),
+                Err(_) => DeviceSelection::Name(value.to_lowercase()),
+            }
+        }
+    }
+
+    /// Picks a device out of the candidates' names, types and graphics support, returning its
+    /// index. The candidates are every device in the order `print_devices` lists them, but only
+    /// devices with graphics support are picked.
+    pub fn select(&self, candidates: &[(String, PhysicalDeviceType, bool)])
+                  -> Result<usize, FrontendError> {
+        match *self {
+            DeviceSelection::Type(preferred) => {
+                // Rank every device, the preferred type always goes first
+                let rank = |ty| if ty == preferred { 5 } else { type_rank(ty) };
+                candidates.iter().enumerate()
+                    .filter(|&(_, c)| c.2)
+                    .max_by_key(|&(i, c)| (rank(c.1), -(i as isize)))
+                    .map(|(i, _)| i)
+                    .ok_or(FrontendError::NoDevice)
+            },
+            DeviceSelection::Index(index) => match candidates.get(index) {
+                Some(c) if c.2 => Ok(index),
+                Some(_) => Err(FrontendError::NoDevice),
+                None => Err(FrontendError::DeviceIndex(index, candidates.len())),
+            },
+            DeviceSelection::Name(ref name) => candidates.iter()
+                .position(|c| c.2 && c.0.to_lowercase().contains(name))
+                .ok_or(FrontendError::NoDevice),
+        }
+    }
+}
+
+impl Default for DeviceSelection {
+    fn default() -> Self {
+        DeviceSelection::Type(PhysicalDeviceType::DiscreteGpu)
+    }
+}
+
+fn type_rank(ty: PhysicalDeviceType) -> i32 {
+    match ty {
+        PhysicalDeviceType::DiscreteGpu => 4,
+        PhysicalDeviceType::IntegratedGpu => 3,
+        PhysicalDeviceType::VirtualGpu => 2,
+        PhysicalDeviceType::Cpu => 1,
+        PhysicalDeviceType::Other => 0,
+    }
+}
+
+/// Creates the Vulkan instance, with validation layers and a debug callback if requested.
+pub fn create_instance(settings: &DeviceSettings)
+                       -> Result<(Arc<Instance>, Option<DebugCallback>), FrontendError> {
+    let mut extensions = vulkano_win::required_extensions();
+    let mut layers = Vec::new();
+
+    if settings.validation {
+        let available = instance::layers_list()
+            .map(|mut list| list.any(|l| l.name() == VALIDATION_LAYER))
+            .unwrap_or(false);
+
+        if available {
+            extensions.ext_debug_report = true;
+            layers.push(VALIDATION_LAYER);
+        } else {
+            println!("Validation layer {} is not available, continuing without", VALIDATION_LAYER);
+        }
+    }
+
+    let instance = try!(Instance::new(None, &extensions, &layers)
+        .map_err(FrontendError::Instance));
+
+    let callback = if extensions.ext_debug_report {
+        DebugCallback::errors_and_warnings(&instance, log_message).ok()
+    } else {
+        None
+    };
+
+    Ok((instance, callback))
+}
+
+fn log_message(message: &Message) {
+    let ty = if message.ty.error { "error" }
+        else if message.ty.warning { "warning" }
+        else if message.ty.performance_warning { "performance" }
+        else { "info" };
+
+    println!("Vulkan {} ({}): {}", ty, message.layer_prefix, message.description);
+}
+
+/// Picks a physical device according to the settings, only considering devices with graphics
+/// support.
+pub fn select_physical<'a>(instance: &'a Arc<Instance>, settings: &DeviceSettings)
+                           -> Result<PhysicalDevice<'a>, FrontendError> {
+    let devices: Vec<_> = PhysicalDevice::enumerate(instance).collect();
+    let candidates: Vec<_> = devices.iter()
+        .map(|d| (d.name(), d.ty(), d.queue_families().any(|q| q.supports_graphics())))
+        .collect();
+
+    settings.selection.select(&candidates).map(|i| devices[i])
+}
+
+/// Prints all devices and their capabilities.
+pub fn print_devices() -> Result<(), FrontendError> {
+    let instance = try!(Instance::new(None, &InstanceExtensions::none(), None)
+        .map_err(FrontendError::Instance));
+
+    for device in PhysicalDevice::enumerate(&instance) {
+        let limits = device.limits();
+
+        println!("{}: {} (type: {:?})", device.index(), device.name(), device.ty());
+        println!("    API version: {:?}, driver version: {}", device.api_version(), device.driver_version());
+        println!("    Vendor ID: 0x{:04x}, device ID: 0x{:04x}", device.pci_vendor_id(), device.pci_device_id());
+        for heap in device.memory_heaps() {
+            println!("    Memory heap {}: {} MiB{}", heap.id(), heap.size() / (1024 * 1024),
+                if heap.is_device_local() { " (device local)" } else { "" });
+        }
+        for family in device.queue_families() {
+            println!("    Queue family {}: {} queues, graphics: {}, compute: {}, transfers: {}",
+                family.id(), family.queues_count(), family.supports_graphics(),
+                family.supports_compute(), family.supports_transfers());
+        }
+        println!("    Max 2D image size: {}, max push constants: {} bytes",
+            limits.max_image_dimension_2d(), limits.max_push_constants_size());
+        println!("    Sample counts: color 0b{:b}, depth 0b{:b}",
+            limits.framebuffer_color_sample_counts(), limits.framebuffer_depth_sample_counts());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::instance::PhysicalDeviceType;
+    use device::DeviceSelection;
+    use error::FrontendError;
+
+    fn hybrid_laptop() -> Vec<(String, PhysicalDeviceType, bool)> {
+        vec![
+            ("Intel HD Graphics 630".into(), PhysicalDeviceType::IntegratedGpu, true),
+            ("llvmpipe".into(), PhysicalDeviceType::Cpu, true),
+            ("NVIDIA GeForce GTX 1060".into(), PhysicalDeviceType::DiscreteGpu, true),
+        ]
+    }
+
+    #[test]
+    fn prefers_discrete_over_integrated() {
+        let selection = DeviceSelection::parse("auto");
+        assert_eq!(selection.select(&hybrid_laptop()).ok(), Some(2));
+    }
+
+    #[test]
+    fn preferred_type_falls_back_to_best_available() {
+        let candidates = vec![
+            ("llvmpipe".into(), PhysicalDeviceType::Cpu, true),
+            ("Intel HD Graphics 630".into(), PhysicalDeviceType::IntegratedGpu, true),
+        ];
+        let selection = DeviceSelection::parse("discrete");
+        assert_eq!(selection.select(&candidates).ok(), Some(1));
+    }
+
+    #[test]
+    fn selects_by_name_or_index() {
+        assert_eq!(DeviceSelection::parse("intel").select(&hybrid_laptop()).ok(), Some(0));
+        assert_eq!(DeviceSelection::parse("1").select(&hybrid_laptop()).ok(), Some(1));
+        assert_eq!(DeviceSelection::parse("radeon").select(&hybrid_laptop()).ok(), None);
+        match DeviceSelection::parse("5").select(&hybrid_laptop()) {
+            Err(FrontendError::DeviceIndex(5, 3)) => (),
+            other => panic!("expected an index error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn skips_devices_without_graphics() {
+        let mut candidates = hybrid_laptop();
+        candidates[2].2 = false;
+        assert_eq!(DeviceSelection::parse("auto").select(&candidates).ok(), Some(0));
+        assert_eq!(DeviceSelection::parse("nvidia").select(&candidates).ok(), None);
+        assert_eq!(DeviceSelection::parse("2").select(&candidates).ok(), None);
+    }
+}
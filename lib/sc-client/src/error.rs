@@ -0,0 +1,207 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use image::ImageError;
+use vulkano::OomError;
+use vulkano::descriptor::pipeline_layout::UnsafePipelineLayoutCreationError;
+use vulkano::device::DeviceCreationError;
+use vulkano::format::Format;
+use vulkano::framebuffer::{FramebufferCreationError, RenderPassCreationError};
+use vulkano::image::ImageCreationError;
+use vulkano::instance::InstanceCreationError;
+use vulkano::pipeline::GraphicsPipelineCreationError;
+use vulkano::sampler::SamplerCreationError;
+use vulkano::swapchain::{AcquireError, PresentError};
+use vulkano::sync::FenceWaitError;
+use vulkano_win;
+
+/// An error in the frontend that prevents it from starting or rendering.
+#[derive(Debug)]
+pub enum FrontendError {
+    Instance(InstanceCreationError),
+    NoDevice,
+    DeviceIndex(usize, usize), // index, device count
+    Window(vulkano_win::CreationError),
+    NoQueueFamily,
+    Device(DeviceCreationError),
+    SurfaceCapabilities(OomError),
+    Swapchain(OomError),
+    Image(ImageCreationError),
+    Texture(String, ImageError), // path
+    Font(String, io::Error), // path
+    Sampler(SamplerCreationError),
+    EmptyMesh,
+    IncompleteTriangle(usize), // index count
+    IndexOutOfRange(u32, usize), // index, vertex count
+    Buffer(OomError),
+    BufferAccess(FenceWaitError),
+    Shader(OomError),
+    ShaderSource(String, io::Error), // path
+    ShaderCompile(String, String), // path, compiler output
+    ShaderInterface(String, String), // path, parts that changed
+    ShaderWatch(String, io::Error), // directory
+    CaptureUsage,
+    CaptureFormat(Format),
+    Screenshot(String, io::Error), // path
+    Session(String, io::Error), // path
+    SessionFormat(String, usize), // path, line
+    RenderPass(RenderPassCreationError),
+    PipelineLayout(UnsafePipelineLayoutCreationError),
+    Pipeline(GraphicsPipelineCreationError),
+    Framebuffer(FramebufferCreationError),
+    Acquire(AcquireError),
+    Submit(OomError),
+    FrameWait(FenceWaitError),
+    Present(PresentError),
+}
+
+impl fmt::Display for FrontendError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            FrontendError::Instance(ref e) =>
+                write!(f, "{}, make sure your graphics drivers support Vulkan ({})", self.description(), e),
+            FrontendError::NoDevice =>
+                write!(f, "{}, check the device settings or run with --list-devices", self.description()),
+            FrontendError::DeviceIndex(index, count) =>
+                write!(f, "{}, device {} is past the {} devices, run with --list-devices",
+                       self.description(), index, count),
+            FrontendError::Texture(ref path, ref e) =>
+                write!(f, "{} \"{}\" ({})", self.description(), path, e),
+            FrontendError::Font(ref path, ref e) =>
+                write!(f, "{} \"{}\" ({})", self.description(), path, e),
+            FrontendError::ShaderSource(ref path, ref e) =>
+                write!(f, "{} \"{}\" ({})", self.description(), path, e),
+            FrontendError::ShaderCompile(ref path, ref output) =>
+                write!(f, "{} \"{}\":\n{}", self.description(), path, output.trim()),
+            FrontendError::ShaderInterface(ref path, ref changed) =>
+                write!(f, "{} \"{}\", its {} changed since the build",
+                       self.description(), path, changed),
+            FrontendError::ShaderWatch(ref directory, ref e) =>
+                write!(f, "{} \"{}\" ({})", self.description(), directory, e),
+            FrontendError::CaptureFormat(format) =>
+                write!(f, "{} ({:?})", self.description(), format),
+            FrontendError::Screenshot(ref path, ref e) =>
+                write!(f, "{} \"{}\" ({})", self.description(), path, e),
+            FrontendError::Session(ref path, ref e) =>
+                write!(f, "{} \"{}\" ({})", self.description(), path, e),
+            FrontendError::SessionFormat(ref path, line) =>
+                write!(f, "{} \"{}\", line {}", self.description(), path, line),
+            FrontendError::NoQueueFamily =>
+                write!(f, "{}, try selecting a different device", self.description()),
+            FrontendError::EmptyMesh | FrontendError::CaptureUsage =>
+                write!(f, "{}", self.description()),
+            FrontendError::IncompleteTriangle(count) =>
+                write!(f, "{}, {} indices is not a multiple of 3", self.description(), count),
+            FrontendError::IndexOutOfRange(index, count) =>
+                write!(f, "{}, index {} is past the {} vertices", self.description(), index, count),
+            FrontendError::Window(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Device(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::SurfaceCapabilities(ref e) |
+            FrontendError::Swapchain(ref e) |
+            FrontendError::Buffer(ref e) |
+            FrontendError::Shader(ref e) |
+            FrontendError::Submit(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Image(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Sampler(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::BufferAccess(ref e) |
+            FrontendError::FrameWait(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::RenderPass(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::PipelineLayout(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Pipeline(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Framebuffer(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Acquire(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+            FrontendError::Present(ref e) =>
+                write!(f, "{} ({})", self.description(), e),
+        }
+    }
+}
+
+impl Error for FrontendError {
+    fn description(&self) -> &str {
+        match *self {
+            FrontendError::Instance(_) => "Unable to create the Vulkan instance",
+            FrontendError::NoDevice => "No suitable graphics device found",
+            FrontendError::DeviceIndex(_, _) => "No graphics device with the selected index",
+            FrontendError::Window(_) => "Unable to create the window",
+            FrontendError::NoQueueFamily => "The graphics device can't draw to the window",
+            FrontendError::Device(_) => "Unable to open the graphics device",
+            FrontendError::SurfaceCapabilities(_) => "Unable to query the window's capabilities",
+            FrontendError::Swapchain(_) => "Unable to create the swapchain",
+            FrontendError::Image(_) => "Unable to create an image",
+            FrontendError::Texture(_, _) => "Unable to load texture",
+            FrontendError::Font(_, _) => "Unable to load font",
+            FrontendError::Sampler(_) => "Unable to create a sampler",
+            FrontendError::EmptyMesh => "Unable to create a mesh without vertices",
+            FrontendError::IncompleteTriangle(_) => "Unable to create a mesh with an incomplete triangle",
+            FrontendError::IndexOutOfRange(_, _) => "Unable to create a mesh with an index out of range",
+            FrontendError::Buffer(_) => "Unable to create a buffer",
+            FrontendError::BufferAccess(_) => "Unable to write to a buffer",
+            FrontendError::Shader(_) => "Unable to load a shader",
+            FrontendError::ShaderSource(_, _) => "Unable to read shader source",
+            FrontendError::ShaderCompile(_, _) => "Unable to compile shader",
+            FrontendError::ShaderInterface(_, _) => "Unable to reload shader",
+            FrontendError::ShaderWatch(_, _) => "Unable to watch shaders in",
+            FrontendError::CaptureUsage => "Unable to copy frames out of the swapchain",
+            FrontendError::CaptureFormat(_) => "Unable to capture frames in the swapchain format",
+            FrontendError::Screenshot(_, _) => "Unable to write screenshot",
+            FrontendError::Session(_, _) => "Unable to access session recording",
+            FrontendError::SessionFormat(_, _) => "Invalid session recording",
+            FrontendError::RenderPass(_) => "Unable to create the render pass",
+            FrontendError::PipelineLayout(_) => "Unable to create the pipeline layout",
+            FrontendError::Pipeline(_) => "Unable to create the graphics pipeline",
+            FrontendError::Framebuffer(_) => "Unable to create a framebuffer",
+            FrontendError::Acquire(_) => "Unable to acquire the next frame",
+            FrontendError::Submit(_) => "Unable to submit the frame to the graphics device",
+            FrontendError::FrameWait(_) => "Timed out waiting for the graphics device",
+            FrontendError::Present(_) => "Unable to present the frame",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error> {
+        match *self {
+            FrontendError::Instance(ref e) => Some(e),
+            FrontendError::Window(ref e) => Some(e),
+            FrontendError::Device(ref e) => Some(e),
+            FrontendError::SurfaceCapabilities(ref e) |
+            FrontendError::Swapchain(ref e) |
+            FrontendError::Buffer(ref e) |
+            FrontendError::Shader(ref e) |
+            FrontendError::Submit(ref e) => Some(e),
+            FrontendError::Image(ref e) => Some(e),
+            FrontendError::Texture(_, ref e) => Some(e),
+            FrontendError::Font(_, ref e) => Some(e),
+            FrontendError::ShaderSource(_, ref e) |
+            FrontendError::ShaderWatch(_, ref e) => Some(e),
+            FrontendError::Screenshot(_, ref e) => Some(e),
+            FrontendError::Session(_, ref e) => Some(e),
+            FrontendError::Sampler(ref e) => Some(e),
+            FrontendError::BufferAccess(ref e) |
+            FrontendError::FrameWait(ref e) => Some(e),
+            FrontendError::RenderPass(ref e) => Some(e),
+            FrontendError::PipelineLayout(ref e) => Some(e),
+            FrontendError::Pipeline(ref e) => Some(e),
+            FrontendError::Framebuffer(ref e) => Some(e),
+            FrontendError::Acquire(ref e) => Some(e),
+            FrontendError::Present(ref e) => Some(e),
+            FrontendError::NoDevice | FrontendError::DeviceIndex(_, _) |
+            FrontendError::NoQueueFamily |
+            FrontendError::EmptyMesh | FrontendError::IncompleteTriangle(_) |
+            FrontendError::IndexOutOfRange(_, _) | FrontendError::ShaderCompile(_, _) |
+            FrontendError::ShaderInterface(_, _) | FrontendError::CaptureUsage |
+            FrontendError::CaptureFormat(_) |
+            FrontendError::SessionFormat(_, _) => None,
+        }
+    }
+}
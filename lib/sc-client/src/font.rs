@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+use std::sync::Arc;
+
+use rusttype::{FontCollection, Scale, point};
+use vulkano::device::{Device, Queue};
+
+use error::FrontendError;
+use material::{self, Texture};
+
+/// Width of the glyph atlas, its height grows to fit all glyphs.
+const ATLAS_WIDTH: u32 = 256;
+
+/// Empty pixels around every glyph, so filtering doesn't bleed neighbouring glyphs in.
+const GLYPH_PADDING: u32 = 1;
+
+/// Size of the solid block in the atlas used for untextured quads.
+const WHITE_SIZE: u32 = 3;
+
+/// Where a glyph is in the atlas, and where to draw it relative to the pen on the baseline.
+#[derive(Copy, Clone, Debug)]
+pub struct Glyph {
+    pub uv_min: [f32; 2],
+    pub uv_max: [f32; 2],
+    pub offset: [f32; 2],
+    pub size: [f32; 2],
+    pub advance: f32,
+}
+
+/// The glyphs baked into an atlas, everything needed to lay out text.
+pub struct GlyphSet {
+    glyphs: HashMap<char, Glyph>,
+    white_uv: [f32; 2],
+    ascent: f32,
+    line_height: f32,
+}
+
+impl GlyphSet {
+    /// Looks up a character's glyph, characters that weren't baked are drawn as '?'.
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    /// A texture coordinate in the atlas that is fully white and opaque.
+    pub fn white_uv(&self) -> [f32; 2] {
+        self.white_uv
+    }
+
+    /// Distance from the top of a line to its baseline.
+    pub fn ascent(&self) -> f32 {
+        self.ascent
+    }
+
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// If there are no glyphs at all, the font failed to load and text can't be drawn.
+    pub fn is_empty(&self) -> bool {
+        self.glyphs.is_empty()
+    }
+
+    /// Calculates the width in pixels of the widest line in the text.
+    pub fn measure(&self, text: &str) -> f32 {
+        text.lines()
+            .map(|line| line.chars().filter_map(|c| self.glyph(c)).map(|g| g.advance).sum::<f32>())
+            .fold(0.0, f32::max)
+    }
+}
+
+/// A font baked at a single size into a texture atlas.
+pub struct Font {
+    texture: Arc<Texture>,
+    glyphs: GlyphSet,
+}
+
+impl Font {
+    /// Loads a TrueType font, baking the printable ASCII characters at the size in pixels.
+    pub fn load(device: &Arc<Device>, queue: &Arc<Queue>, path: &str, size: f32)
+                -> Result<Self, FrontendError> {
+        let mut data = Vec::new();
+        try!(File::open(path).and_then(|mut file| file.read_to_end(&mut data))
+            .map_err(|e| FrontendError::Font(path.to_string(), e)));
+
+        let (glyphs, dimensions, pixels) = try!(bake(&data, size).ok_or_else(|| FrontendError::Font(
+            path.to_string(), io::Error::new(io::ErrorKind::InvalidData, "not a valid TrueType font")
+        )));
+        let texture = try!(material::upload_texture(
+            device, queue, dimensions[0], dimensions[1], &pixels
+        ));
+
+        Ok(Font {
+            texture: texture,
+            glyphs: glyphs,
+        })
+    }
+
+    /// A font without any glyphs, text drawn with it is invisible but untextured quads still work.
+    pub fn empty(device: &Arc<Device>, queue: &Arc<Queue>) -> Result<Self, FrontendError> {
+        Ok(Font {
+            texture: try!(material::solid_texture(device, queue, [255, 255, 255, 255])),
+            glyphs: GlyphSet {
+                glyphs: HashMap::new(),
+                white_uv: [0.5, 0.5],
+                ascent: 0.0,
+                line_height: 0.0,
+            },
+        })
+    }
+
+    pub fn texture(&self) -> &Arc<Texture> {
+        &self.texture
+    }
+
+    pub fn glyphs(&self) -> &GlyphSet {
+        &self.glyphs
+    }
+}
+
+/// Rasterizes the printable ASCII characters of a TrueType font into RGBA atlas pixels, returning
+/// None if the data isn't a valid font.
+pub fn bake(data: &[u8], size: f32) -> Option<(GlyphSet, [u32; 2], Vec<u8>)> {
+    let font = match FontCollection::from_bytes(data).into_font() {
+        Some(font) => font,
+        None => return None,
+    };
+    let scale = Scale::uniform(size);
+    let v_metrics = font.v_metrics(scale);
+
+    // Rasterize every character into its own coverage bitmap, whitespace has no bitmap at all
+    let mut rasterized = Vec::new();
+    for c in (0x20u8..0x7F).map(|c| c as char) {
+        let glyph = match font.glyph(c) {
+            Some(glyph) => glyph.scaled(scale).positioned(point(0.0, 0.0)),
+            None => continue,
+        };
+        let advance = glyph.unpositioned().h_metrics().advance_width;
+
+        let (offset, size, coverage) = match glyph.pixel_bounding_box() {
+            Some(bb) => {
+                let (width, height) = (bb.width() as u32, bb.height() as u32);
+                let mut coverage = vec![0u8; (width * height) as usize];
+                glyph.draw(|x, y, v| coverage[(y * width + x) as usize] = (v * 255.0).round() as u8);
+                ([bb.min.x as f32, bb.min.y as f32], [width, height], coverage)
+            },
+            None => ([0.0, 0.0], [0, 0], Vec::new()),
+        };
+
+        rasterized.push((c, offset, size, coverage, advance));
+    }
+
+    // Place the white block first, followed by all glyphs
+    let sizes: Vec<_> = Some([WHITE_SIZE, WHITE_SIZE]).into_iter()
+        .chain(rasterized.iter().map(|r| r.2))
+        .collect();
+    let (positions, height) = pack(&sizes, ATLAS_WIDTH);
+    let width = ATLAS_WIDTH;
+
+    // The atlas is white everywhere, glyphs are only stored in the alpha channel
+    let mut pixels: Vec<u8> = (0..width * height).flat_map(|_| vec![255, 255, 255, 0]).collect();
+    blit(&mut pixels, width, positions[0], sizes[0], &vec![255u8; (WHITE_SIZE * WHITE_SIZE) as usize]);
+
+    let uv = |x: u32, y: u32| [x as f32 / width as f32, y as f32 / height as f32];
+    let mut glyphs = HashMap::new();
+    for (&(c, offset, size, ref coverage, advance), &position) in rasterized.iter().zip(positions[1..].iter()) {
+        blit(&mut pixels, width, position, size, coverage);
+        glyphs.insert(c, Glyph {
+            uv_min: uv(position[0], position[1]),
+            uv_max: uv(position[0] + size[0], position[1] + size[1]),
+            offset: offset,
+            size: [size[0] as f32, size[1] as f32],
+            advance: advance,
+        });
+    }
+
+    // Sample the center of the white block, so filtering never reaches its edges
+    let center = |start: u32| start as f32 + WHITE_SIZE as f32 / 2.0;
+    let glyph_set = GlyphSet {
+        glyphs: glyphs,
+        white_uv: [center(positions[0][0]) / width as f32, center(positions[0][1]) / height as f32],
+        ascent: v_metrics.ascent,
+        line_height: (v_metrics.ascent - v_metrics.descent + v_metrics.line_gap).ceil(),
+    };
+
+    Some((glyph_set, [width, height], pixels))
+}
+
+/// Copies a coverage bitmap into the alpha channel of the atlas pixels.
+fn blit(pixels: &mut [u8], width: u32, position: [u32; 2], size: [u32; 2], coverage: &[u8]) {
+    for y in 0..size[1] {
+        for x in 0..size[0] {
+            let i = ((position[1] + y) * width + position[0] + x) as usize;
+            pixels[i * 4 + 3] = coverage[(y * size[0] + x) as usize];
+        }
+    }
+}
+
+/// Packs rectangles into rows of the given width, returning their positions and the total height
+/// rounded up to a power of two.
+fn pack(sizes: &[[u32; 2]], width: u32) -> (Vec<[u32; 2]>, u32) {
+    let mut positions = Vec::with_capacity(sizes.len());
+    let mut x = GLYPH_PADDING;
+    let mut y = GLYPH_PADDING;
+    let mut row_height = 0;
+
+    for size in sizes {
+        // Start a new row if this rectangle doesn't fit on the current one
+        if x + size[0] + GLYPH_PADDING > width {
+            x = GLYPH_PADDING;
+            y += row_height + GLYPH_PADDING;
+            row_height = 0;
+        }
+
+        positions.push([x, y]);
+        x += size[0] + GLYPH_PADDING;
+        row_height = row_height.max(size[1]);
+    }
+
+    let height = (y + row_height + GLYPH_PADDING).next_power_of_two();
+    (positions, height)
+}
+
+#[cfg(test)]
+mod tests {
+    use font::{bake, pack};
+
+    const FONT: &'static [u8] = include_bytes!("../../../assets/fonts/DejaVuSansMono.ttf");
+
+    #[test]
+    fn packed_rectangles_do_not_overlap() {
+        let sizes = vec![[30, 10], [30, 20], [30, 5], [50, 8]];
+        let (positions, height) = pack(&sizes, 64);
+
+        assert!(height.is_power_of_two());
+        for (i, (a, sa)) in positions.iter().zip(sizes.iter()).enumerate() {
+            assert!(a[0] + sa[0] <= 64 && a[1] + sa[1] <= height);
+            for (b, sb) in positions.iter().zip(sizes.iter()).skip(i + 1) {
+                let separate = a[0] + sa[0] <= b[0] || b[0] + sb[0] <= a[0] ||
+                    a[1] + sa[1] <= b[1] || b[1] + sb[1] <= a[1];
+                assert!(separate, "{:?} and {:?} overlap", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn bakes_printable_ascii() {
+        let (glyphs, dimensions, pixels) = bake(FONT, 16.0).unwrap();
+
+        assert_eq!(pixels.len() as u32, dimensions[0] * dimensions[1] * 4);
+        for c in (0x20u8..0x7F).map(|c| c as char) {
+            assert!(glyphs.glyph(c).is_some(), "{:?} missing", c);
+        }
+
+        // The font is monospaced, so every line is as wide as its amount of characters
+        let advance = glyphs.glyph('a').unwrap().advance;
+        assert!(advance > 0.0);
+        assert_eq!(glyphs.measure("abc\nde"), advance * 3.0);
+    }
+}
@@ -4,8 +4,10 @@ pub struct FrameCounter {
     last: Instant,
     last_delta: f32,
 
-    last_fps_logged: Instant,
+    last_measured: Instant,
     frame_counter: i32,
+    fps: f32,
+    frame_time: f32,
 }
 
 impl FrameCounter {
@@ -16,29 +18,46 @@ impl FrameCounter {
             last: now,
             last_delta: 0.016,
 
-            last_fps_logged: now,
+            last_measured: now,
             frame_counter: 0,
+            fps: 0.0,
+            frame_time: 0.0,
         }
     }
 
     pub fn tick(&mut self) {
-        // Increment the counter for the FPS logging and store the elapsed delta
+        // Increment the counter for the FPS measurement and store the elapsed delta
         self.frame_counter += 1;
         let now = Instant::now();
-        let elapsed = now.duration_since(self.last);
-        self.last_delta = (elapsed.as_secs() as f32) + (elapsed.subsec_nanos() as f32 / 1_000_000_000.0);
+        self.last_delta = seconds(now.duration_since(self.last));
         self.last = now;
 
-        // Keep track of the FPS and log it every second
-        let elapsed_since_logged = now.duration_since(self.last_fps_logged);
-        if elapsed_since_logged >= Duration::new(1, 0) {
-            println!("FPS: {}", self.frame_counter);
+        // Measure the FPS and average frame time every second
+        let elapsed_since_measured = now.duration_since(self.last_measured);
+        if elapsed_since_measured >= Duration::new(1, 0) {
+            let elapsed = seconds(elapsed_since_measured);
+            self.fps = self.frame_counter as f32 / elapsed;
+            self.frame_time = elapsed / self.frame_counter as f32;
             self.frame_counter = 0;
-            self.last_fps_logged += elapsed_since_logged;
+            self.last_measured += elapsed_since_measured;
         }
     }
 
     pub fn delta(&self) -> f32 {
         self.last_delta
     }
+
+    /// Frames per second, measured over the last full second.
+    pub fn fps(&self) -> f32 {
+        self.fps
+    }
+
+    /// Average seconds per frame, measured over the last full second.
+    pub fn frame_time(&self) -> f32 {
+        self.frame_time
+    }
+}
+
+fn seconds(duration: Duration) -> f32 {
+    (duration.as_secs() as f32) + (duration.subsec_nanos() as f32 / 1_000_000_000.0)
 }
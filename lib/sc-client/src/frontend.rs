@@ -1,28 +1,57 @@
+use std::cmp;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, SystemTime};
 
-use cgmath::{self, Matrix4, Vector2, Vector3, Rad, SquareMatrix};
-use winit::{Event, ElementState, VirtualKeyCode, WindowBuilder};
+use cgmath::{self, Matrix4, Vector2, Vector3, Deg, SquareMatrix, InnerSpace};
+use glsl_to_spirv::ShaderType;
+use winit::{self, Event, ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode};
+use winit::WindowBuilder;
 use vulkano;
+use vulkano::buffer::TypedBuffer;
 use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
 use vulkano::command_buffer::{self, DynamicState, PrimaryCommandBufferBuilder, Submission};
 use vulkano::device::Device;
 use vulkano::descriptor::descriptor_set::DescriptorPool;
-use vulkano::format::D16Unorm;
+use vulkano::format::Format;
 use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::ImageCreationError;
 use vulkano::image::attachment::AttachmentImage;
-use vulkano::instance::{Instance, PhysicalDevice};
+use vulkano::image::swapchain::SwapchainImage;
+use vulkano::instance::debug::DebugCallback;
 use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineParams};
-use vulkano::pipeline::vertex::TwoBuffersDefinition;
+use vulkano::pipeline::vertex::OneVertexOneInstanceDefinition;
 use vulkano::pipeline::raster::{Rasterization, CullMode, FrontFace};
-use vulkano::swapchain::{Swapchain, PresentMode};
+use vulkano::pipeline::viewport::{Viewport, Scissor};
+use vulkano::sampler::Sampler;
+use vulkano::swapchain::{Swapchain, AcquireError, PresentError};
 use vulkano_win::{self, VkSurfaceBuild};
 
-use sc_client_game::ClientWorld;
-use sc_input_data::Button;
-use {vs, fs, teapot};
+use sc_client_game::{ClientWorld, Model, Lighting, Atmosphere, DayCycle};
+use sc_input_data::{Button, CursorMode, TextInput};
+use bounds::Frustum;
+use cursor::CursorTracker;
+use debuglines::{self, DebugPass, DebugVertex};
+use device::{self, DeviceSettings};
+use error::FrontendError;
+use font::Font;
+use hud::{self, HudStats};
+use material::{self, Material, MaterialDesc, DEFAULT_MATERIAL};
+use mesh::{self, Mesh, MeshPool, IndexSlice, Vertex, Instance};
+use meshgen;
+use meshtools;
+use overlay::{OverlayPass, QuadBatch, QuadVertex};
+use postprocess::{self, PostChain, PostTargets};
+use screenshot::{self, Capture};
+use settings::GraphicsSettings;
+use shaderreload::{self, ShaderWatcher};
+use shadow::{self, Cascade, ShadowMap, ShadowPass};
+use sky::SkyPass;
+use {vs, fs, shadow_vs, shadow_fs, sky_vs, sky_fs};
 
-mod renderpass {
+pub mod renderpass {
     single_pass_renderpass!{
         attachments: {
             color: {
@@ -33,7 +62,7 @@ mod renderpass {
             depth: {
                 load: Clear,
                 store: DontCare,
-                format: ::vulkano::format::D16Unorm,
+                format: ::vulkano::format::Format,
             }
         },
         pass: {
@@ -43,220 +72,627 @@ mod renderpass {
     }
 }
 
-mod pipeline_layout {
+pub mod pipeline_layout {
     pipeline_layout!{
         set0: {
-            uniforms: UniformBuffer<::vs::ty::Data>
+            uniforms: UniformBuffer<::vs::ty::Data>,
+            lighting: UniformBuffer<::fs::ty::Lighting>,
+            shadow: UniformBuffer<::fs::ty::Shadow>,
+            shadow_map: CombinedImageSampler
+        },
+        set1: {
+            material: UniformBuffer<::fs::ty::Material>,
+            albedo: CombinedImageSampler
         }
     }
 }
 
+/// The maximum amount of frames the CPU is allowed to queue up ahead of the GPU.
+const FRAMES_IN_FLIGHT: usize = 2;
+
+/// One image more than double buffering, so acquiring the next one rarely has to wait.
+const SWAPCHAIN_IMAGES: u32 = 3;
+
+/// Has to match the define in the fragment shader, a test checks that it does.
+const MAX_POINT_LIGHTS: usize = 8;
+
+/// Touchpads scroll in pixels, this turns them into wheel lines so both scroll the same.
+const PIXELS_PER_LINE: f32 = 20.0;
+
+const FONT_PATH: &'static str = "assets/fonts/DejaVuSansMono.ttf";
+
+/// Offscreen frames are rendered in a linear format that can always be drawn to and captured.
+const OFFSCREEN_FORMAT: Format = Format::R8G8B8A8Unorm;
+
+type ScenePipeline = GraphicsPipeline<OneVertexOneInstanceDefinition<Vertex, Instance>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>;
+
+/// How many entities were drawn this frame out of all entities in the world, shown on the HUD.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CullingStats {
+    pub total: usize,
+    pub visible: usize,
+    pub shadow_casters: usize,
+}
+
+/// Where finished frames end up.
+enum Output {
+    /// Presented in the window.
+    Swapchain(Arc<Swapchain>, Vec<Arc<SwapchainImage>>),
+    /// Rendered to an image for every frame in flight and never shown, for replays that only
+    /// save their frames. This doesn't need a surface that can present or be copied from.
+    Offscreen(Vec<Arc<AttachmentImage<Format>>>),
+}
+
+impl Output {
+    fn format(&self) -> Format {
+        match *self {
+            Output::Swapchain(_, ref images) => images[0].format(),
+            Output::Offscreen(_) => OFFSCREEN_FORMAT,
+        }
+    }
+
+    fn dimensions(&self) -> [u32; 2] {
+        match *self {
+            Output::Swapchain(_, ref images) => images[0].dimensions(),
+            Output::Offscreen(ref images) => images[0].dimensions(),
+        }
+    }
+}
+
+/// Resources that are rewritten every frame, one of these exists for every frame in flight.
+struct Frame {
+    uniform_buffer: Arc<CpuAccessibleBuffer<vs::ty::Data>>,
+    lighting_buffer: Arc<CpuAccessibleBuffer<fs::ty::Lighting>>,
+    shadow_buffer: Arc<CpuAccessibleBuffer<fs::ty::Shadow>>,
+    shadow_map: ShadowMap,
+    post_targets: PostTargets,
+    framebuffer: Arc<Framebuffer<renderpass::CustomRenderPass>>,
+    set: Arc<pipeline_layout::set0::Set>,
+    instance_buffers: HashMap<(Model, usize), Arc<CpuAccessibleBuffer<[Instance]>>>,
+    shadow_instance_buffers: HashMap<Model, Arc<CpuAccessibleBuffer<[Instance]>>>,
+    debug_buffer: Option<Arc<CpuAccessibleBuffer<[DebugVertex]>>>,
+    overlay_buffer: Option<Arc<CpuAccessibleBuffer<[QuadVertex]>>>,
+    capture: Option<Capture>,
+    submission: Option<Arc<Submission>>,
+}
+
+impl Frame {
+    fn new(
+        device: &Arc<Device>, queue: &Arc<vulkano::device::Queue>,
+        pool: &Arc<DescriptorPool>, layout: &Arc<pipeline_layout::CustomPipeline>,
+        renderpass: &Arc<renderpass::CustomRenderPass>, depth_buffer: &Arc<AttachmentImage<Format>>,
+        shadow_pass: &ShadowPass, post_chain: &PostChain
+    ) -> Result<Self, FrontendError> {
+        let uniform_buffer = try!(unsafe {
+            CpuAccessibleBuffer::<vs::ty::Data>::uninitialized(
+                device, &vulkano::buffer::BufferUsage::all(), Some(queue.family())
+            ).map_err(FrontendError::Buffer)
+        });
+
+        let lighting_buffer = try!(unsafe {
+            CpuAccessibleBuffer::<fs::ty::Lighting>::uninitialized(
+                device, &vulkano::buffer::BufferUsage::all(), Some(queue.family())
+            ).map_err(FrontendError::Buffer)
+        });
+
+        let shadow_buffer = try!(unsafe {
+            CpuAccessibleBuffer::<fs::ty::Shadow>::uninitialized(
+                device, &vulkano::buffer::BufferUsage::all(), Some(queue.family())
+            ).map_err(FrontendError::Buffer)
+        });
+
+        // Every frame gets its own shadow map, so the next frame's shadow pass can't overwrite it
+        // while it's still being read
+        let shadow_map = try!(shadow_pass.create_map(device));
+
+        // The scene is rendered to the frame's HDR image, which post-processing reads from
+        let post_targets = try!(post_chain.create_targets());
+        let dimensions = post_targets.hdr().dimensions();
+        let framebuffer = try!(Framebuffer::new(
+            renderpass, [dimensions[0], dimensions[1], 1],
+            renderpass::AList { color: post_targets.hdr(), depth: depth_buffer }
+        ).map_err(FrontendError::Framebuffer));
+
+        let set = pipeline_layout::set0::Set::new(pool, layout, &pipeline_layout::set0::Descriptors {
+            uniforms: &uniform_buffer,
+            lighting: &lighting_buffer,
+            shadow: &shadow_buffer,
+            shadow_map: (shadow_pass.sampler(), shadow_map.image()),
+        });
+
+        Ok(Frame {
+            uniform_buffer: uniform_buffer,
+            lighting_buffer: lighting_buffer,
+            shadow_buffer: shadow_buffer,
+            shadow_map: shadow_map,
+            post_targets: post_targets,
+            framebuffer: framebuffer,
+            set: set,
+            instance_buffers: HashMap::new(),
+            shadow_instance_buffers: HashMap::new(),
+            debug_buffer: None,
+            overlay_buffer: None,
+            capture: None,
+            submission: None,
+        })
+    }
+}
+
 pub struct Frontend {
     window: vulkano_win::Window,
     dimensions: Vector2<i32>,
+    cursor: CursorTracker,
+    text_input: bool,
+    skip_char: bool,
+    settings: GraphicsSettings,
+    _debug_callback: Option<DebugCallback>,
 
     device: Arc<Device>,
     queue: Arc<vulkano::device::Queue>,
+    descriptor_pool: Arc<DescriptorPool>,
 
-    vertex_buffer: Arc<CpuAccessibleBuffer<[teapot::Vertex]>>,
-    normals_buffer: Arc<CpuAccessibleBuffer<[teapot::Normal]>>,
-    index_buffer: Arc<CpuAccessibleBuffer<[u16]>>,
+    meshes: HashMap<Model, Mesh>,
+    materials: Vec<Material>,
+    material_indices: HashMap<String, usize>,
 
-    pipeline: Arc<GraphicsPipeline<TwoBuffersDefinition<teapot::Vertex, teapot::Normal>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>>,
     pipeline_layout: Arc<pipeline_layout::CustomPipeline>,
-    descriptor_pool: Arc<DescriptorPool>,
+    pipeline: Arc<ScenePipeline>,
+    shader_watcher: Option<ShaderWatcher>,
+    shadow_pass: ShadowPass,
+    sky_pass: SkyPass,
+    debug_pass: DebugPass,
+    post_chain: PostChain,
+    overlay_pass: OverlayPass,
+    font: Font,
 
-    framebuffers: Vec<Arc<Framebuffer<renderpass::CustomRenderPass>>>,
     renderpass: Arc<renderpass::CustomRenderPass>,
-    swapchain: Arc<Swapchain>,
-    submissions: Vec<Arc<Submission>>,
+    output: Output,
+    /// Set when the window was resized or the swapchain went out of date, it's recreated before
+    /// the next frame.
+    swapchain_stale: bool,
+    frames: Vec<Frame>,
+    current_frame: usize,
+    copyable_images: bool,
+    capture_request: Option<PathBuf>,
 }
 
 impl Frontend {
-    pub fn init() -> Self {
-        let extensions = vulkano_win::required_extensions();
-        let instance = Instance::new(None, &extensions, None)
-            .expect("failed to create instance");
+    /// Opens the window and sets up rendering. Offscreen, frames are only rendered to be
+    /// captured, the window stays hidden and is just there for its events.
+    pub fn init(
+        mut settings: GraphicsSettings, device_settings: &DeviceSettings,
+        material_descs: &[(String, MaterialDesc)], offscreen: bool
+    ) -> Result<Self, FrontendError> {
+        let (instance, debug_callback) = try!(device::create_instance(device_settings));
 
-        let physical = PhysicalDevice::enumerate(&instance)
-            .next()
-            .expect("no device available");
+        let physical = try!(device::select_physical(&instance, device_settings));
         println!("Using device: {} (type: {:?})", physical.name(), physical.ty());
 
-        let dimensions = Vector2::new(1280, 720);
-        let window = WindowBuilder::new()
-            .with_dimensions(dimensions.x as u32, dimensions.y as u32)
-            .build_vk_surface(&instance).unwrap();
+        // Fit the window on the monitor up front, rather than resize it once it's open
+        let monitor = winit::get_primary_monitor();
+        if !offscreen {
+            settings.fit_monitor(monitor.get_dimensions());
+        }
+
+        let mut builder = WindowBuilder::new()
+            .with_dimensions(settings.resolution.x, settings.resolution.y)
+            .with_visibility(!offscreen);
+        if settings.fullscreen && !offscreen {
+            builder = builder.with_fullscreen(monitor);
+        }
+        let window = try!(builder.build_vk_surface(&instance).map_err(FrontendError::Window));
 
-        let queue_families = physical.queue_families()
-            .find(|q| q.supports_graphics() && window.surface().is_supported(q).unwrap_or(false))
-            .expect("couldn't find a graphical queue family");
+        let queue_families = try!(physical.queue_families()
+            .find(|q| {
+                q.supports_graphics() &&
+                    (offscreen || window.surface().is_supported(q).unwrap_or(false))
+            })
+            .ok_or(FrontendError::NoQueueFamily));
 
         let device_ext = vulkano::device::DeviceExtensions {
-            khr_swapchain: true,
+            khr_swapchain: !offscreen,
             .. vulkano::device::DeviceExtensions::none()
         };
 
-        let (device, mut queues) = Device::new(
+        let (device, mut queues) = try!(Device::new(
             &physical, physical.supported_features(),
             &device_ext, [(queue_families, 0.5)].iter().cloned()
-        ).expect("failed to create device");
+        ).map_err(FrontendError::Device));
         let queue = queues.next().unwrap();
 
-        let (swapchain, images) = {
-            let caps = window.surface().get_capabilities(&physical).expect("failed to get surface capabilities");
+        let (output, copyable_images) = if offscreen {
+            settings.validate(&physical, None);
 
-            let dimensions = caps.current_extent.unwrap_or([1280, 1024]);
-            let present = PresentMode::Fifo;
+            // Offscreen images are made to be copied from, whatever the surface allows
+            let images = try!((0..FRAMES_IN_FLIGHT)
+                .map(|_| AttachmentImage::new(&device, settings.resolution.into(), OFFSCREEN_FORMAT)
+                    .map_err(FrontendError::Image))
+                .collect::<Result<Vec<_>, _>>());
+            (Output::Offscreen(images), true)
+        } else {
+            let caps = try!(window.surface().get_capabilities(&physical)
+                .map_err(FrontendError::SurfaceCapabilities));
+            let requested = settings.resolution;
+            settings.validate(&physical, Some(&caps));
+
+            // The surface's limits can still be tighter than the monitor
+            let dimensions = if settings.resolution != requested {
+                window.window().set_inner_size(settings.resolution.x, settings.resolution.y);
+                settings.resolution.into()
+            } else {
+                caps.current_extent.unwrap_or(settings.resolution.into())
+            };
+            let present = settings.present_mode;
             let usage = caps.supported_usage_flags;
-            let format = caps.supported_formats[0].0;
+            // Prefer a linear format, gamma correction is done while post-processing
+            let format = caps.supported_formats.iter()
+                .map(|&(format, _)| format)
+                .find(|&format| format == Format::B8G8R8A8Unorm || format == Format::R8G8B8A8Unorm)
+                .unwrap_or(caps.supported_formats[0].0);
 
-            Swapchain::new(
-                &device, &window.surface(), 3, format, dimensions, 1,
+            let (swapchain, images) = try!(Swapchain::new(
+                &device, &window.surface(), SWAPCHAIN_IMAGES, format, dimensions, 1,
                 &usage, &queue, vulkano::swapchain::SurfaceTransform::Identity,
                 vulkano::swapchain::CompositeAlpha::Opaque,
                 present, true, None
-            ).expect("failed to create swapchain")
-        };
+            ).map_err(FrontendError::Swapchain));
 
-        let depth_buffer = AttachmentImage::transient(
-            &device, images[0].dimensions(), D16Unorm
-        ).unwrap();
-
-        let vertex_buffer = unsafe {
-            CpuAccessibleBuffer::uninitialized_array(
-                &device,
-                teapot::VERTICES.len(),
-                &vulkano::buffer::BufferUsage::all(),
-                Some(queue.family())
-            ).expect("failed to create buffer")
-        };
-
-        {
-            let mut mapping = vertex_buffer.write(Duration::new(0, 0)).unwrap();
-            for (o, i) in mapping.iter_mut().zip(teapot::VERTICES.iter()) {
-                *o = *i;
+            // Screenshots copy from the swapchain images, not every surface allows that
+            if let Err(e) = screenshot::check_support(usage.transfer_source, format) {
+                println!("{}, screenshots are disabled", e);
             }
-        }
-
-        let normals_buffer = unsafe {
-            CpuAccessibleBuffer::uninitialized_array(
-                &device, teapot::NORMALS.len(),
-                &vulkano::buffer::BufferUsage::all(), Some(queue.family())
-            ).expect("failed to create buffer")
+            (Output::Swapchain(swapchain, images), usage.transfer_source)
         };
+        let output_dimensions = output.dimensions();
 
-        {
-            let mut mapping = normals_buffer.write(Duration::new(0, 0)).unwrap();
-            for (o, i) in mapping.iter_mut().zip(teapot::NORMALS.iter()) {
-                *o = *i;
-            }
+        // Every device can use D16 as depth attachment, other formats are optional. This image
+        // only checks the format, the frames create their own depth buffers
+        match AttachmentImage::transient(&device, output_dimensions, settings.depth_format) {
+            Err(ImageCreationError::FormatNotSupported) |
+            Err(ImageCreationError::UnsupportedUsage) => {
+                println!("Depth format {:?} not supported by device, using D16Unorm",
+                    settings.depth_format);
+                settings.depth_format = Format::D16Unorm;
+            },
+            result => { try!(result.map_err(FrontendError::Image)); },
         }
 
-        let index_buffer = unsafe {
-            CpuAccessibleBuffer::uninitialized_array(
-                &device, teapot::INDICES.len(),
-                &vulkano::buffer::BufferUsage::all(), Some(queue.family())
-            ).expect("failed to create buffer")
-        };
-
-        {
-            let mut mapping = index_buffer.write(Duration::new(0, 0)).unwrap();
-            for (o, i) in mapping.iter_mut().zip(teapot::INDICES.iter()) {
-                *o = *i;
-            }
+        // Generate, prepare and upload all the meshes the world can refer to
+        let detail = settings.mesh_detail;
+        let generated = vec![
+            (Model::Teapot, meshgen::teapot(detail)),
+            (Model::Cube, meshgen::cube(1.0)),
+            (Model::Plane, meshgen::plane(1.0, 1)),
+            (Model::Sphere, meshgen::sphere(0.5, detail * 4, detail * 2)),
+            (Model::Capsule, meshgen::capsule(0.25, 0.5, detail * 4, detail * 2)),
+        ];
+        let mut pool = MeshPool::new();
+        let mut models = Vec::new();
+        for (model, mut data) in generated {
+            meshtools::prepare(&mut data);
+            try!(pool.add(&data));
+            models.push(model);
         }
+        let meshes: HashMap<_, _> = models.into_iter()
+            .zip(try!(pool.upload(&device, &queue)))
+            .collect();
 
-        let vs = vs::Shader::load(&device).expect("failed to create shader module");
-        let fs = fs::Shader::load(&device).expect("failed to create shader module");
+        let vs = try!(vs::Shader::load(&device).map_err(FrontendError::Shader));
+        let fs = try!(fs::Shader::load(&device).map_err(FrontendError::Shader));
 
-        let renderpass = renderpass::CustomRenderPass::new(&device, &renderpass::Formats {
-            color: (images[0].format(), 1),
-            depth: (vulkano::format::D16Unorm, 1)
-        }).unwrap();
+        let renderpass = try!(renderpass::CustomRenderPass::new(&device, &renderpass::Formats {
+            color: (postprocess::HDR_FORMAT, 1),
+            depth: (settings.depth_format, 1)
+        }).map_err(FrontendError::RenderPass));
 
         let descriptor_pool = vulkano::descriptor::descriptor_set::DescriptorPool::new(&device);
 
-        let pipeline_layout = pipeline_layout::CustomPipeline::new(&device).unwrap();
-
-        let pipeline = GraphicsPipeline::new(&device, GraphicsPipelineParams {
-            vertex_input: vulkano::pipeline::vertex::TwoBuffersDefinition::new(),
-            vertex_shader: vs.main_entry_point(),
-            input_assembly: vulkano::pipeline::input_assembly::InputAssembly::triangle_list(),
-            tessellation: None,
-            geometry_shader: None,
-            viewport: vulkano::pipeline::viewport::ViewportsState::Fixed {
-                data: vec![(
-                    vulkano::pipeline::viewport::Viewport {
-                        origin: [0.0, 0.0],
-                        depth_range: 0.0 .. 1.0,
-                        dimensions: [images[0].dimensions()[0] as f32, images[0].dimensions()[1] as f32],
-                    },
-                    vulkano::pipeline::viewport::Scissor::irrelevant()
-                )],
-            },
-            raster: Rasterization {
-                cull_mode: CullMode::Back,
-                front_face: FrontFace::Clockwise, // This seems to cull CCW, I'm not sure why
-                .. Default::default()
+        let pipeline_layout = try!(pipeline_layout::CustomPipeline::new(&device)
+            .map_err(FrontendError::PipelineLayout));
+
+        let pipeline = try!(create_pipeline(&device, &renderpass, &pipeline_layout, &vs, &fs));
+
+        let (materials, material_indices) = try!(load_materials(
+            &device, &queue, &descriptor_pool, &pipeline_layout, material_descs
+        ));
+
+        let shadow_pass = try!(ShadowPass::new(&device, &settings));
+        let sky_pass = try!(SkyPass::new(&device, &queue, &renderpass));
+        let debug_pass = try!(DebugPass::new(&device, &renderpass));
+
+        // The HUD is still worth drawing without text, so a missing font isn't fatal
+        let font = match Font::load(&device, &queue, FONT_PATH, settings.hud_font_size) {
+            Ok(font) => font,
+            Err(e @ FrontendError::Font(_, _)) => {
+                println!("{}, the HUD will only show the crosshair", e);
+                try!(Font::empty(&device, &queue))
             },
-            multisample: vulkano::pipeline::multisample::Multisample::disabled(),
-            fragment_shader: fs.main_entry_point(),
-            depth_stencil: vulkano::pipeline::depth_stencil::DepthStencil::simple_depth_test(),
-            blend: vulkano::pipeline::blend::Blend::pass_through(),
-            layout: &pipeline_layout,
-            render_pass: Subpass::from(&renderpass, 0).unwrap(),
-        }).unwrap();
-
-        let framebuffers: Vec<_> = images.iter().map(|image| {
-            let attachments = renderpass::AList {
-                color: &image,
-                depth: &depth_buffer,
-            };
+            Err(e) => return Err(e),
+        };
+        let (post_chain, overlay_pass) = try!(create_output_passes(
+            &device, &queue, &settings, &output, &font
+        ));
+
+        let frames = try!(create_frames(
+            &device, &queue, &descriptor_pool, &pipeline_layout, &renderpass,
+            settings.depth_format, output_dimensions, &shadow_pass, &post_chain
+        ));
 
-            Framebuffer::new(
-                &renderpass,
-                [image.dimensions()[0], image.dimensions()[1], 1],
-                attachments
-            ).unwrap()
-        }).collect();
+        let dimensions = Vector2::new(output_dimensions[0] as i32, output_dimensions[1] as i32);
 
-        Frontend {
+        Ok(Frontend {
             window: window,
             dimensions: dimensions,
+            // Left alone until the game asks for something else
+            cursor: CursorTracker::new(CursorMode::Free),
+            text_input: false,
+            skip_char: false,
+            settings: settings,
+            _debug_callback: debug_callback,
 
             device: device,
             queue: queue,
+            descriptor_pool: descriptor_pool,
 
-            vertex_buffer: vertex_buffer,
-            normals_buffer: normals_buffer,
-            index_buffer: index_buffer,
+            meshes: meshes,
+            materials: materials,
+            material_indices: material_indices,
 
-            pipeline: pipeline,
             pipeline_layout: pipeline_layout,
-            descriptor_pool: descriptor_pool,
+            pipeline: pipeline,
+            shader_watcher: None,
+            shadow_pass: shadow_pass,
+            sky_pass: sky_pass,
+            debug_pass: debug_pass,
+            post_chain: post_chain,
+            overlay_pass: overlay_pass,
+            font: font,
 
-            framebuffers: framebuffers,
             renderpass: renderpass,
-            swapchain: swapchain,
-            submissions: Vec::new(),
+            output: output,
+            swapchain_stale: false,
+            frames: frames,
+            current_frame: 0,
+            copyable_images: copyable_images,
+            capture_request: None,
+        })
+    }
+
+    /// Saves the next frame that is drawn to a PNG file, if the output allows capturing.
+    pub fn capture_frame(&mut self, path: PathBuf) -> Result<(), FrontendError> {
+        try!(self.capture_support());
+        self.capture_request = Some(path);
+        Ok(())
+    }
+
+    /// Saves the next frame that is drawn as a screenshot, named after the current time.
+    pub fn screenshot(&mut self) {
+        let directory = Path::new(screenshot::SCREENSHOT_DIR);
+        let path = screenshot::screenshot_path(directory, SystemTime::now());
+        if let Err(e) = self.capture_frame(path) {
+            println!("{}, skipping the screenshot", e);
         }
     }
 
-    pub fn poll_events<H: FnMut(FrontendEvent)>(&self, mut handler: H) {
+    fn capture_support(&self) -> Result<(), FrontendError> {
+        screenshot::check_support(self.copyable_images, self.output.format())
+    }
+
+    /// Waits for every frame in flight to finish, writing out any captured ones.
+    pub fn finish_captures(&mut self) -> Result<(), FrontendError> {
+        for frame in &mut self.frames {
+            if let Some(submission) = frame.submission.take() {
+                try!(submission.wait(Duration::from_secs(5)).map_err(FrontendError::FrameWait));
+            }
+            if let Some(capture) = frame.capture.take() {
+                save_capture(&capture);
+            }
+        }
+        Ok(())
+    }
+
+    /// Starts watching the GLSL sources in the directory, recompiling shaders and rebuilding the
+    /// pipelines using them when they change.
+    pub fn watch_shaders(&mut self, directory: &Path) -> Result<(), FrontendError> {
+        self.shader_watcher = Some(try!(ShaderWatcher::new(directory)));
+        Ok(())
+    }
+
+    /// Rebuilds the pipelines whose shaders changed. A shader that fails to compile is reported
+    /// and the previous pipeline is kept, so a typo doesn't end the session.
+    fn reload_shaders(&mut self) {
+        let (changed, directory) = match self.shader_watcher {
+            Some(ref mut watcher) => (watcher.poll(), watcher.directory().to_path_buf()),
+            None => return,
+        };
+        if changed.is_empty() {
+            return;
+        }
+
+        let device = self.device.clone();
+        let compile_pair = |files: &[(&str, &[u8]); 2]| -> Result<_, FrontendError> {
+            let (vs_name, vs_spirv) = files[0];
+            let (fs_name, fs_spirv) = files[1];
+            Ok((
+                try!(shaderreload::compile(
+                    &device, &directory.join(vs_name), ShaderType::Vertex, vs_spirv
+                )),
+                try!(shaderreload::compile(
+                    &device, &directory.join(fs_name), ShaderType::Fragment, fs_spirv
+                )),
+            ))
+        };
+        let touched = |files: &[(&str, &[u8]); 2]| {
+            changed.iter().any(|c| files.iter().any(|&(name, _)| name == c))
+        };
+
+        // Modules with a different interface are rejected, the unsafe from_module relies on it
+        let scene: [(&str, &[u8]); 2] = [
+            ("shader_vs.glsl", vs::SPIRV), ("shader_fs.glsl", fs::SPIRV)
+        ];
+        if touched(&scene) {
+            let result = compile_pair(&scene)
+                .and_then(|(vs_module, fs_module)| unsafe {
+                    create_pipeline(
+                        &device, &self.renderpass, &self.pipeline_layout,
+                        &vs::Shader::from_module(vs_module), &fs::Shader::from_module(fs_module)
+                    )
+                });
+            report_reload("scene", result.map(|pipeline| self.pipeline = pipeline));
+        }
+
+        let shadow: [(&str, &[u8]); 2] = [
+            ("shader_shadow_vs.glsl", shadow_vs::SPIRV), ("shader_shadow_fs.glsl", shadow_fs::SPIRV)
+        ];
+        if touched(&shadow) {
+            let result = compile_pair(&shadow)
+                .and_then(|(vs_module, fs_module)| unsafe {
+                    self.shadow_pass.reload(
+                        &device, &shadow_vs::Shader::from_module(vs_module),
+                        &shadow_fs::Shader::from_module(fs_module)
+                    )
+                });
+            report_reload("shadow", result);
+        }
+
+        let sky: [(&str, &[u8]); 2] = [
+            ("shader_sky_vs.glsl", sky_vs::SPIRV), ("shader_sky_fs.glsl", sky_fs::SPIRV)
+        ];
+        if touched(&sky) {
+            let result = compile_pair(&sky)
+                .and_then(|(vs_module, fs_module)| unsafe {
+                    self.sky_pass.reload(
+                        &device, &sky_vs::Shader::from_module(vs_module),
+                        &sky_fs::Shader::from_module(fs_module)
+                    )
+                });
+            report_reload("sky", result);
+        }
+
+        // The remaining passes still only use the shaders compiled by the build script
+        let reloadable: Vec<_> = scene.iter().chain(&shadow).chain(&sky)
+            .map(|&(name, _)| name)
+            .collect();
+        for name in changed.iter().filter(|c| !reloadable.contains(&c.as_str())) {
+            println!("Shader \"{}\" changed, it can't be reloaded yet and needs a rebuild", name);
+        }
+    }
+
+    /// Switches the cursor to the mode, a grab is held off until the window has focus.
+    pub fn set_cursor_mode(&mut self, mode: CursorMode) {
+        self.cursor.set_mode(mode);
+        self.apply_cursor();
+    }
+
+    /// Switches the keyboard between gameplay buttons and typing into a text field. The keys that
+    /// close a field stay buttons either way, so there's always a way out.
+    pub fn set_text_input(&mut self, enabled: bool) {
+        self.text_input = enabled;
+    }
+
+    fn apply_cursor(&self) {
+        if let Err(e) = self.window.window().set_cursor_state(self.cursor.window_state()) {
+            println!("Unable to change the cursor ({})", e);
+        }
+    }
+
+    pub fn poll_events<H: FnMut(FrontendEvent)>(&mut self, mut handler: H) {
+        // F12 does nothing if screenshots aren't supported, that was reported on startup
+        let can_capture = self.capture_support().is_ok();
+
         // Handle the window's events
         for ev in self.window.window().poll_events() {
             match ev {
                 Event::Closed => handler(FrontendEvent::Close),
+                Event::Resized(..) => self.swapchain_stale = true,
+                Event::Focused(focused) => {
+                    self.cursor.set_focused(focused);
+                    self.apply_cursor();
+                    handler(FrontendEvent::Focus(focused));
+                },
+                Event::MouseInput(state, button) => {
+                    // Clicking into the window takes back a grab lost to alt-tabbing, that click
+                    // shouldn't also fire
+                    let down = state == ElementState::Pressed;
+                    if down && self.cursor.click() {
+                        self.apply_cursor();
+                        continue;
+                    }
+
+                    // Unlike keys these stay buttons while typing, chat lets firing through and
+                    // the input contexts in the game decide what reaches gameplay
+                    let button = match button {
+                        MouseButton::Left => Some(Button::Fire),
+                        MouseButton::Right => Some(Button::Aim),
+                        _ => None
+                    };
+                    if let Some(button) = button {
+                        handler(FrontendEvent::ButtonState(button, down));
+                    }
+                },
+                Event::MouseWheel(delta, _) => {
+                    // Scrolling is sent while typing too, the input contexts decide where it goes
+                    let lines = match delta {
+                        MouseScrollDelta::LineDelta(x, y) => Vector2::new(x, y),
+                        MouseScrollDelta::PixelDelta(x, y) =>
+                            Vector2::new(x, y) / PIXELS_PER_LINE,
+                    };
+                    handler(FrontendEvent::Scroll(lines));
+                },
+                Event::KeyboardInput(ElementState::Pressed, _, Some(VirtualKeyCode::F12))
+                    if can_capture => handler(FrontendEvent::Screenshot),
+                Event::ReceivedCharacter(c) => {
+                    // The character of a key that went out as a button follows it, when that
+                    // button opened or closed a text field it mustn't end up in there
+                    if self.skip_char {
+                        self.skip_char = false;
+                        continue;
+                    }
+
+                    // Control characters arrive for the editing keys handled below and for
+                    // shortcuts. Ctrl+V doesn't paste, winit has no clipboard access
+                    if self.text_input && !c.is_control() {
+                        handler(FrontendEvent::Text(TextInput::Char(c)));
+                    }
+                },
+                Event::KeyboardInput(state, _, Some(key))
+                    if self.text_input && !closes_text_input(key) => {
+                    if state == ElementState::Pressed {
+                        self.skip_char = false;
+                    }
+
+                    // Key repeat sends more presses, so held editing keys keep going
+                    let text = match key {
+                        VirtualKeyCode::Back => Some(TextInput::Backspace),
+                        VirtualKeyCode::Delete => Some(TextInput::Delete),
+                        VirtualKeyCode::Left => Some(TextInput::Left),
+                        VirtualKeyCode::Right => Some(TextInput::Right),
+                        VirtualKeyCode::Up => Some(TextInput::Up),
+                        VirtualKeyCode::Down => Some(TextInput::Down),
+                        VirtualKeyCode::Home => Some(TextInput::Home),
+                        VirtualKeyCode::End => Some(TextInput::End),
+                        _ => None
+                    };
+
+                    if let (ElementState::Pressed, Some(text)) = (state, text) {
+                        handler(FrontendEvent::Text(text));
+                    }
+                },
                 Event::KeyboardInput(state, _, Some(key)) => {
                     // Translate the keyboard event to a button event
                     let down = state == ElementState::Pressed;
+                    if down {
+                        self.skip_char = true;
+                    }
                     let button = match key {
                         VirtualKeyCode::W => Some(Button::MoveForward),
                         VirtualKeyCode::S => Some(Button::MoveBackward),
                         VirtualKeyCode::D => Some(Button::MoveRight),
                         VirtualKeyCode::A => Some(Button::MoveLeft),
                         VirtualKeyCode::Escape => Some(Button::Menu),
+                        VirtualKeyCode::Grave => Some(Button::Console),
+                        VirtualKeyCode::T => Some(Button::Chat),
+                        VirtualKeyCode::Return => Some(Button::Confirm),
                         _ => None
                     };
 
@@ -266,32 +702,121 @@ impl Frontend {
                     }
                 },
                 Event::MouseMoved(position) => {
+                    let center = (self.dimensions[0]/2, self.dimensions[1]/2);
+
+                    // A free cursor is only moved by the player, so there's nothing to track
+                    if !self.cursor.is_grabbed() {
+                        handler(FrontendEvent::MouseMove(position.into(), false));
+                        continue;
+                    }
+
                     // First, send an event for the initial mouse move
                     handler(FrontendEvent::MouseMove(position.into(), true));
 
-                    // Now, move the mouse back and also send an event for that
-                    let new = (self.dimensions[0]/2, self.dimensions[1]/2);
-                    self.window.window().set_cursor_position(new.0, new.1).unwrap();
-                    handler(FrontendEvent::MouseMove(new.into(), false));
+                    // Now, move the mouse back and also send an event for that, unless it's
+                    // the event caused by doing exactly that
+                    if position != center {
+                        let _ = self.window.window().set_cursor_position(center.0, center.1);
+                        handler(FrontendEvent::MouseMove(center.into(), false));
+                    }
                 },
                 _ => {}
             }
         }
     }
 
-    pub fn render(&mut self, world: &ClientWorld) {
-        // Remove all command buffers that the GPU is finished with
-        self.submissions.retain(|s| s.destroying_would_block());
+    /// Replaces the swapchain with one fitting the window, along with everything sized after
+    /// its images. Returns false while the window has no area to draw to, like when minimized.
+    fn recreate_swapchain(&mut self) -> Result<bool, FrontendError> {
+        // The frames in flight still use the old images
+        try!(self.finish_captures());
+
+        let physical = self.device.physical_device();
+        let caps = try!(self.window.surface().get_capabilities(&physical)
+            .map_err(FrontendError::SurfaceCapabilities));
+        let size = self.window.window().get_inner_size_pixels().unwrap_or((0, 0));
+        let dimensions = caps.current_extent.unwrap_or([size.0, size.1]);
+        if dimensions[0] == 0 || dimensions[1] == 0 {
+            return Ok(false);
+        }
+        let dimensions = [
+            cmp::max(caps.min_image_extent[0], cmp::min(dimensions[0], caps.max_image_extent[0])),
+            cmp::max(caps.min_image_extent[1], cmp::min(dimensions[1], caps.max_image_extent[1])),
+        ];
+
+        let output = match self.output {
+            Output::Swapchain(ref old, ref images) => {
+                let (swapchain, images) = try!(Swapchain::new(
+                    &self.device, &self.window.surface(), SWAPCHAIN_IMAGES, images[0].format(),
+                    dimensions, 1, &caps.supported_usage_flags, &self.queue,
+                    vulkano::swapchain::SurfaceTransform::Identity,
+                    vulkano::swapchain::CompositeAlpha::Opaque,
+                    self.settings.present_mode, true, Some(old)
+                ).map_err(FrontendError::Swapchain));
+                Output::Swapchain(swapchain, images)
+            },
+            // Offscreen images don't depend on the window
+            Output::Offscreen(_) => return Ok(true),
+        };
+
+        let (post_chain, overlay_pass) = try!(create_output_passes(
+            &self.device, &self.queue, &self.settings, &output, &self.font
+        ));
+        let frames = try!(create_frames(
+            &self.device, &self.queue, &self.descriptor_pool, &self.pipeline_layout,
+            &self.renderpass, self.settings.depth_format, dimensions, &self.shadow_pass, &post_chain
+        ));
+
+        self.dimensions = Vector2::new(dimensions[0] as i32, dimensions[1] as i32);
+        self.output = output;
+        self.post_chain = post_chain;
+        self.overlay_pass = overlay_pass;
+        self.frames = frames;
+        self.current_frame = 0;
+        Ok(true)
+    }
+
+    pub fn render(&mut self, world: &ClientWorld, stats: &HudStats) -> Result<(), FrontendError> {
+        self.reload_shaders();
+
+        // Nothing is drawn until the swapchain fits the window again
+        if self.swapchain_stale {
+            if !try!(self.recreate_swapchain()) {
+                return Ok(());
+            }
+            self.swapchain_stale = false;
+        }
+
+        // Wait for the GPU to be done with the oldest frame in flight, so we can reuse its resources
+        let frame = &mut self.frames[self.current_frame];
+        if let Some(submission) = frame.submission.take() {
+            try!(submission.wait(Duration::from_secs(5)).map_err(FrontendError::FrameWait));
+        }
+        if let Some(capture) = frame.capture.take() {
+            save_capture(&capture);
+        }
 
         // Aquire ownership of the next frame's image to work on
-        let image_num = self.swapchain.acquire_next_image(Duration::from_millis(1))
-            .expect("Unable to aquire swapchain image in time.");
+        // If no image is available in time we skip this frame rather than stall
+        let image_num = match self.output {
+            Output::Swapchain(ref swapchain, _) =>
+                match swapchain.acquire_next_image(Duration::from_millis(1)) {
+                    Ok(num) => num,
+                    Err(AcquireError::Timeout) => return Ok(()),
+                    Err(AcquireError::OutOfDate) => {
+                        self.swapchain_stale = true;
+                        return Ok(());
+                    },
+                    Err(e) => return Err(FrontendError::Acquire(e)),
+                },
+            // Offscreen every frame in flight has its own image, free once its frame finished
+            Output::Offscreen(_) => self.current_frame,
+        };
 
         // Calculate the camera projection matrix
+        let aspect = self.dimensions[0] as f32 / self.dimensions[1] as f32;
         let mut proj = cgmath::perspective(
-            Rad(::std::f32::consts::PI * 0.5),
-            { let d = &self.dimensions; d[0] as f32 / d[1] as f32 },
-            0.01, 100.0
+            Deg(self.settings.fov), aspect, self.settings.near, self.settings.far
         );
 
         // Correct the projection for the inverted Y in vulkan
@@ -302,59 +827,424 @@ impl Frontend {
         let translation = Matrix4::from_translation(cam.position());
         let rotation_pitch = Matrix4::from_angle_x(cam.pitch());
         let rotation_yaw = Matrix4::from_angle_y(cam.yaw());
-        let view = (translation * rotation_yaw * rotation_pitch).invert().unwrap();
+        let camera = translation * rotation_yaw * rotation_pitch;
+        let view = camera.invert().unwrap();
 
-        // Calculate the teapot model matrix
-        let translation = Matrix4::from_translation(Vector3::new(0.0, 0.0, 0.0));
-        let rotation = Matrix4::identity();
-        let scale = Matrix4::from_scale(0.01);
-        let model = translation * rotation * scale;
+        {
+            let mut mapping = try!(frame.uniform_buffer.write(Duration::new(0, 0))
+                .map_err(FrontendError::BufferAccess));
+            mapping.view = view.into();
+            mapping.proj = proj.into();
+        }
 
-        let uniform_buffer = unsafe {
-            CpuAccessibleBuffer::<vs::ty::Data>::uninitialized(
-                &self.device,
-                &vulkano::buffer::BufferUsage::all(),
-                Some(self.queue.family())
-            ).expect("failed to create buffer")
+        {
+            let mut mapping = try!(frame.lighting_buffer.write(Duration::new(0, 0))
+                .map_err(FrontendError::BufferAccess));
+            write_lighting(&mut mapping, world.lighting(), &view, cam.position());
+            write_fog(&mut mapping, world.day_cycle(), world.atmosphere());
+        }
+
+        // Fit the shadow cascades around the parts of the view they cover
+        let cascades = if self.shadow_pass.enabled() {
+            shadow::cascades(&self.settings, &camera, aspect, world.lighting().sun.direction)
+        } else {
+            Vec::new()
         };
 
         {
-            let mut mapping = uniform_buffer.write(Duration::new(0, 0)).unwrap();
-            mapping.worldview = (view * model).into();
-            mapping.proj = proj.into();
+            let mut mapping = try!(frame.shadow_buffer.write(Duration::new(0, 0))
+                .map_err(FrontendError::BufferAccess));
+            write_shadow(&mut mapping, &cascades, &camera, &self.settings);
         }
 
-        let set = pipeline_layout::set0::Set::new(
-            &self.descriptor_pool, &self.pipeline_layout,
-            &pipeline_layout::set0::Descriptors {
-                uniforms: &uniform_buffer
+        // Skip entities outside of the camera's view, shadow casters are checked against the
+        // cascades instead since entities out of view can still cast shadows into it
+        let camera_frustum = Frustum::from_matrix(&(proj * view));
+        let cascade_frustums: Vec<_> = cascades.iter()
+            .map(|c| Frustum::from_matrix(&c.view_proj))
+            .collect();
+        let mut culling = CullingStats::default();
+
+        // Group the entities by model and material so every group can be drawn in a single
+        // instanced call, unknown materials are drawn with the default material
+        // Shadows don't use materials, so casters are only grouped by model
+        let mut instances = HashMap::new();
+        let mut casters = HashMap::new();
+        for entity in world.entities() {
+            let model = entity.transform().matrix();
+            let bounds = self.meshes[&entity.model()].bounds();
+            let instance = Instance {
+                i_model: model.into(),
+            };
+            culling.total += 1;
+
+            if camera_frustum.intersects(bounds, &model) {
+                culling.visible += 1;
+                let material = self.material_indices.get(entity.material()).cloned().unwrap_or(0);
+                instances.entry((entity.model(), material)).or_insert_with(Vec::new).push(instance);
             }
-        );
 
-        // Build up the command buffer we want to submit for this frame
-        let buffer = PrimaryCommandBufferBuilder::new(&self.device, self.queue.family())
-            .draw_inline(&self.renderpass, &self.framebuffers[image_num], renderpass::ClearValues {
-                 color: [0.1, 0.1, 0.1, 1.0],
+            if cascade_frustums.iter().any(|f| f.intersects(bounds, &model)) {
+                culling.shadow_casters += 1;
+                casters.entry(entity.model()).or_insert_with(Vec::new).push(instance);
+            }
+        }
+
+        let instance_buffers = try!(write_instance_groups(
+            &self.device, &self.queue, &mut frame.instance_buffers, instances
+        ));
+        let caster_buffers = try!(write_instance_groups(
+            &self.device, &self.queue, &mut frame.shadow_instance_buffers, casters
+        ));
+
+        // Same for the debug lines and HUD, which change every frame
+        let debug_vertices = debuglines::vertices(world.debug().lines());
+        try!(mesh::write_or_upload(&self.device, &self.queue, &mut frame.debug_buffer, &debug_vertices));
+
+        let mut overlay = QuadBatch::new(self.font.glyphs().white_uv());
+        if self.settings.hud {
+            let dimensions = [self.dimensions.x as u32, self.dimensions.y as u32];
+            hud::build(&mut overlay, self.font.glyphs(), stats, &culling, dimensions);
+        }
+        try!(mesh::write_or_upload(&self.device, &self.queue, &mut frame.overlay_buffer, overlay.vertices()));
+
+        // Build up the command buffer we want to submit for this frame, starting with the shadows
+        let shadow_draws: Vec<_> = caster_buffers.iter()
+            .map(|&(model, ref instance_buffer)| (&self.meshes[&model], instance_buffer))
+            .collect();
+        let builder = PrimaryCommandBufferBuilder::new(&self.device, self.queue.family());
+        let builder = self.shadow_pass.draw(builder, &frame.shadow_map, &cascades, &shadow_draws);
+
+        let horizon = world.day_cycle().horizon_color();
+        let builder = builder
+            .draw_inline(&self.renderpass, &frame.framebuffer, renderpass::ClearValues {
+                 color: [horizon.x, horizon.y, horizon.z, 1.0],
                  depth: 1.0,
-            })
-            .draw_indexed(
-                &self.pipeline, (&self.vertex_buffer, &self.normals_buffer), &self.index_buffer,
-                &DynamicState::none(), &set, &()
-            )
-            .draw_end()
-            .build();
+            });
+        let dynamic = full_viewport(self.output.dimensions());
+        let mut builder = self.sky_pass.draw(builder, &dynamic, &view, &proj, world);
+        for &((model, material), ref instance_buffer) in &instance_buffers {
+            let mesh = &self.meshes[&model];
+            let material = &self.materials[material];
+            let vertices = (mesh.vertex_buffer(), instance_buffer);
+            let sets = (&frame.set, material.set());
+            builder = match mesh.indices() {
+                IndexSlice::U16(indices) => builder.draw_indexed(
+                    &self.pipeline, vertices, indices, &dynamic, sets, &()
+                ),
+                IndexSlice::U32(indices) => builder.draw_indexed(
+                    &self.pipeline, vertices, indices, &dynamic, sets, &()
+                ),
+            };
+        }
+        if let Some(ref debug_buffer) = frame.debug_buffer {
+            builder = self.debug_pass.draw(builder, &dynamic, debug_buffer, &(proj * view));
+        }
+        let builder = builder.draw_end();
 
-        // Submit the command buffer and keep track of the submission so we can clean it up later
-        let submission = command_buffer::submit(&buffer, &self.queue).unwrap();
-        self.submissions.push(submission);
+        // Turn the HDR scene into the final image on the output
+        let mut builder = self.post_chain.draw(builder, &frame.post_targets, image_num);
+
+        // Draw the HUD on top of the final image
+        if let Some(ref overlay_buffer) = frame.overlay_buffer {
+            builder = self.overlay_pass.draw(builder, overlay_buffer, image_num);
+        }
+
+        // Copy the final image out before it's presented, it's written to disk once the frame
+        // has finished rendering
+        if let Some(path) = self.capture_request.take() {
+            let dimensions = self.output.dimensions();
+            let format = self.output.format();
+            match Capture::new(&self.device, &self.queue, dimensions, format, path) {
+                Ok(capture) => {
+                    let extent = [dimensions[0], dimensions[1], 1];
+                    builder = match self.output {
+                        Output::Swapchain(_, ref images) => builder.copy_color_image_to_buffer(
+                            capture.buffer(), &images[image_num], 0, 0 .. 1, [0, 0, 0], extent
+                        ),
+                        Output::Offscreen(ref images) => builder.copy_color_image_to_buffer(
+                            capture.buffer(), &images[image_num], 0, 0 .. 1, [0, 0, 0], extent
+                        ),
+                    };
+                    frame.capture = Some(capture);
+                },
+                Err(e) => println!("{}, skipping the capture", e),
+            }
+        }
+        let buffer = builder.build();
+
+        // Submit the command buffer and keep track of the submission so we can wait on it later
+        let submission = try!(command_buffer::submit(&buffer, &self.queue)
+            .map_err(FrontendError::Submit));
+        frame.submission = Some(submission);
 
         // Present our new frame to the user
-        self.swapchain.present(&self.queue, image_num).unwrap();
+        if let Output::Swapchain(ref swapchain, _) = self.output {
+            match swapchain.present(&self.queue, image_num) {
+                Ok(()) => {},
+                Err(PresentError::OutOfDate) => self.swapchain_stale = true,
+                Err(e) => return Err(FrontendError::Present(e)),
+            }
+        }
+        self.current_frame = (self.current_frame + 1) % FRAMES_IN_FLIGHT;
+
+        Ok(())
+    }
+}
+
+/// Creates the pipeline drawing the scene's meshes, from either the built-in shaders or ones
+/// reloaded at runtime.
+fn create_pipeline(
+    device: &Arc<Device>, renderpass: &Arc<renderpass::CustomRenderPass>,
+    layout: &Arc<pipeline_layout::CustomPipeline>, vs: &vs::Shader, fs: &fs::Shader
+) -> Result<Arc<ScenePipeline>, FrontendError> {
+    GraphicsPipeline::new(device, GraphicsPipelineParams {
+        vertex_input: OneVertexOneInstanceDefinition::new(),
+        vertex_shader: vs.main_entry_point(),
+        input_assembly: vulkano::pipeline::input_assembly::InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: vulkano::pipeline::viewport::ViewportsState::Dynamic { num: 1 },
+        raster: Rasterization {
+            cull_mode: CullMode::Back,
+            front_face: FrontFace::Clockwise, // This seems to cull CCW, I'm not sure why
+            .. Default::default()
+        },
+        multisample: vulkano::pipeline::multisample::Multisample::disabled(),
+        fragment_shader: fs.main_entry_point(),
+        depth_stencil: vulkano::pipeline::depth_stencil::DepthStencil::simple_depth_test(),
+        blend: vulkano::pipeline::blend::Blend::pass_through(),
+        layout: layout,
+        render_pass: Subpass::from(renderpass, 0).unwrap(),
+    }).map_err(FrontendError::Pipeline)
+}
+
+/// The viewport the scene, sky and debug pipelines draw with, it's set while drawing so they
+/// survive the output being resized.
+fn full_viewport(dimensions: [u32; 2]) -> DynamicState {
+    DynamicState {
+        line_width: None,
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+            depth_range: 0.0 .. 1.0,
+        }]),
+        scissors: Some(vec![Scissor::irrelevant()]),
+    }
+}
+
+/// Creates the passes drawing to the output's images, these are recreated with the swapchain.
+fn create_output_passes(
+    device: &Arc<Device>, queue: &Arc<vulkano::device::Queue>, settings: &GraphicsSettings,
+    output: &Output, font: &Font
+) -> Result<(PostChain, OverlayPass), FrontendError> {
+    let format = output.format();
+    let dimensions = output.dimensions();
+    Ok(match *output {
+        Output::Swapchain(_, ref images) => (
+            try!(PostChain::new(device, queue, settings, images, format, dimensions)),
+            try!(OverlayPass::new(device, images, format, dimensions, font)),
+        ),
+        Output::Offscreen(ref images) => (
+            try!(PostChain::new(device, queue, settings, images, format, dimensions)),
+            try!(OverlayPass::new(device, images, format, dimensions, font)),
+        ),
+    })
+}
+
+/// Creates the resources of every frame in flight. Depth is cleared and thrown away every frame,
+/// but two frames can still be rendering at the same time, so each gets its own depth buffer.
+fn create_frames(
+    device: &Arc<Device>, queue: &Arc<vulkano::device::Queue>, pool: &Arc<DescriptorPool>,
+    layout: &Arc<pipeline_layout::CustomPipeline>, renderpass: &Arc<renderpass::CustomRenderPass>,
+    depth_format: Format, dimensions: [u32; 2], shadow_pass: &ShadowPass, post_chain: &PostChain
+) -> Result<Vec<Frame>, FrontendError> {
+    (0..FRAMES_IN_FLIGHT)
+        .map(|_| {
+            let depth_buffer = try!(AttachmentImage::transient(device, dimensions, depth_format)
+                .map_err(FrontendError::Image));
+            Frame::new(
+                device, queue, pool, layout, renderpass, &depth_buffer, shadow_pass, post_chain
+            )
+        })
+        .collect()
+}
+
+fn save_capture(capture: &Capture) {
+    match capture.save() {
+        Ok(()) => println!("Saved {}", capture.path().display()),
+        Err(e) => println!("{}", e),
+    }
+}
+
+fn report_reload(name: &str, result: Result<(), FrontendError>) {
+    match result {
+        Ok(()) => println!("Reloaded the {} shaders", name),
+        Err(e) => println!("{}\nKeeping the previous {} shaders", e, name),
+    }
+}
+
+/// Uploads all materials and their textures, returning them along with their indices by name.
+/// The default material is always at index 0, unless overridden it's plain white.
+fn load_materials(
+    device: &Arc<Device>, queue: &Arc<vulkano::device::Queue>,
+    pool: &Arc<DescriptorPool>, layout: &Arc<pipeline_layout::CustomPipeline>,
+    descs: &[(String, MaterialDesc)]
+) -> Result<(Vec<Material>, HashMap<String, usize>), FrontendError> {
+    let sampler = Sampler::simple_repeat_linear_no_mipmap(device);
+    let white = try!(material::solid_texture(device, queue, [255, 255, 255, 255]));
+
+    let default = (DEFAULT_MATERIAL.to_string(), MaterialDesc::default());
+    let descs = Some(&default).into_iter().chain(descs.iter());
+
+    let mut materials = Vec::new();
+    let mut indices = HashMap::new();
+    let mut textures = HashMap::new();
+    for &(ref name, ref desc) in descs {
+        // Textures can be shared between materials, so only load every texture once
+        let texture = match desc.texture {
+            Some(ref path) => {
+                if !textures.contains_key(path) {
+                    // Missing or broken art shouldn't stop the game, but GPU errors should
+                    let texture = match material::load_texture(device, queue, path) {
+                        Ok(texture) => texture,
+                        Err(e @ FrontendError::Texture(_, _)) => {
+                            println!("{}, using a white texture", e);
+                            white.clone()
+                        },
+                        Err(e) => return Err(e),
+                    };
+                    textures.insert(path.clone(), texture);
+                }
+                textures[path].clone()
+            },
+            None => white.clone(),
+        };
+
+        let material = try!(Material::new(device, queue, pool, layout, desc, &texture, &sampler));
+
+        // Materials defined later replace earlier ones with the same name
+        match indices.get(name).cloned() {
+            Some(index) => materials[index] = material,
+            None => {
+                indices.insert(name.clone(), materials.len());
+                materials.push(material);
+            },
+        }
+    }
+
+    Ok((materials, indices))
+}
+
+/// Fills the shadow uniforms, converting the cascades to map from view space to the shadow map.
+fn write_shadow(data: &mut fs::ty::Shadow, cascades: &[Cascade], camera: &Matrix4<f32>,
+                settings: &GraphicsSettings) {
+    for (i, cascade) in cascades.iter().enumerate() {
+        let atlas = shadow::atlas_matrix(i, cascades.len());
+        data.cascade_matrices[i] = (atlas * cascade.view_proj * *camera).into();
+        data.cascade_splits[i] = cascade.split;
+    }
+
+    let resolution = settings.shadow_resolution as f32;
+    data.texel_size = [1.0 / (resolution * cascades.len().max(1) as f32), 1.0 / resolution];
+    data.cascade_count = cascades.len() as i32;
+    data.pcf_radius = settings.shadow_pcf as i32;
+}
+
+/// Fills the fog part of the lighting uniforms, distant geometry fades into the horizon's color.
+fn write_fog(data: &mut fs::ty::Lighting, day_cycle: &DayCycle, atmosphere: &Atmosphere) {
+    data.fog_color = day_cycle.horizon_color().extend(atmosphere.fog_density).into();
+}
+
+/// Rewrites the instance buffers for every group, they only need to be reallocated if the amount
+/// of instances changed since draw calls use the full buffer.
+fn write_instance_groups<K: Hash + Eq + Copy>(
+    device: &Arc<Device>, queue: &Arc<vulkano::device::Queue>,
+    buffers: &mut HashMap<K, Arc<CpuAccessibleBuffer<[Instance]>>>, groups: HashMap<K, Vec<Instance>>
+) -> Result<Vec<(K, Arc<CpuAccessibleBuffer<[Instance]>>)>, FrontendError> {
+    let mut written = Vec::new();
+    for (key, instances) in groups {
+        let reusable = buffers.get(&key)
+            .map(|b| b.len() == instances.len())
+            .unwrap_or(false);
+
+        if reusable {
+            try!(mesh::write(&buffers[&key], &instances));
+        } else {
+            let buffer = try!(mesh::upload(device, queue, &instances));
+            buffers.insert(key, buffer);
+        }
+
+        written.push((key, buffers[&key].clone()));
+    }
+
+    // Buffers of groups that left the scene would otherwise be kept around forever
+    let stale: Vec<_> = buffers.keys()
+        .filter(|&key| !written.iter().any(|&(k, _)| k == *key))
+        .cloned()
+        .collect();
+    for key in stale {
+        buffers.remove(&key);
+    }
+
+    Ok(written)
+}
+
+/// Fills the lighting uniforms, converting all lights to view space.
+fn write_lighting(data: &mut fs::ty::Lighting, lighting: &Lighting, view: &Matrix4<f32>,
+                  camera: Vector3<f32>) {
+    let sun = &lighting.sun;
+    data.sun_direction = (*view * (-sun.direction).extend(0.0)).into();
+    data.sun_color = (sun.color * sun.intensity).extend(1.0).into();
+    data.ambient_color = lighting.ambient.extend(1.0).into();
+
+    // If there's more point lights than the shader supports, use the ones closest to the camera
+    let mut point_lights: Vec<_> = lighting.point_lights.iter().collect();
+    point_lights.sort_by(|a, b| {
+        let a = (a.position - camera).magnitude2();
+        let b = (b.position - camera).magnitude2();
+        a.partial_cmp(&b).unwrap_or(::std::cmp::Ordering::Equal)
+    });
+    point_lights.truncate(MAX_POINT_LIGHTS);
+
+    for (i, light) in point_lights.iter().enumerate() {
+        let mut position = *view * light.position.extend(1.0);
+        position.w = light.range;
+        data.point_positions[i] = position.into();
+        data.point_colors[i] = (light.color * light.intensity).extend(1.0).into();
     }
+    data.point_count = point_lights.len() as i32;
+}
+
+/// Escape and the console key, still sent as buttons while typing.
+/// Keys that stay buttons while typing, the game closes the text field with them.
+fn closes_text_input(key: VirtualKeyCode) -> bool {
+    key == VirtualKeyCode::Escape || key == VirtualKeyCode::Grave ||
+        key == VirtualKeyCode::Return
 }
 
 pub enum FrontendEvent {
     Close,
+    Screenshot,
     ButtonState(Button, bool),
     MouseMove(Vector2<i32>, bool), // position, should be tracked for frame offset
+    Text(TextInput),
+    Scroll(Vector2<f32>), // in lines, positive is away from the player and to the right
+    Focus(bool),
+}
+
+#[cfg(test)]
+mod tests {
+    use frontend::MAX_POINT_LIGHTS;
+
+    #[test]
+    fn point_light_limit_matches_shader() {
+        let source = include_str!("shader_fs.glsl");
+        let define = source.lines()
+            .filter_map(|l| l.trim().split("#define MAX_POINT_LIGHTS").nth(1))
+            .next()
+            .expect("The fragment shader doesn't define MAX_POINT_LIGHTS");
+
+        assert_eq!(define.trim().parse::<usize>(), Ok(MAX_POINT_LIGHTS));
+    }
 }
@@ -0,0 +1,48 @@
+use font::GlyphSet;
+use frontend::CullingStats;
+use overlay::QuadBatch;
+
+const MARGIN: f32 = 8.0;
+const TEXT_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 1.0];
+const BACKGROUND_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 0.5];
+
+const CROSSHAIR_SIZE: f32 = 8.0; // from the center to the end of a line
+const CROSSHAIR_THICKNESS: f32 = 2.0;
+const CROSSHAIR_COLOR: [f32; 4] = [1.0, 1.0, 1.0, 0.8];
+
+/// Numbers shown on the HUD, gathered by the main loop every frame.
+#[derive(Copy, Clone, Debug)]
+pub struct HudStats {
+    pub fps: f32,
+    pub frame_time: f32, // in seconds
+}
+
+/// Lays out the HUD's performance and culling numbers and crosshair for a screen of the given size.
+/// Without glyphs to draw the numbers with only the crosshair is shown.
+pub fn build(batch: &mut QuadBatch, glyphs: &GlyphSet, stats: &HudStats, culling: &CullingStats,
+             dimensions: [u32; 2]) {
+    if !glyphs.is_empty() {
+        let text = format!(
+            "FPS: {:.0}\nFrame: {:.1} ms\nDrawn: {}/{} ({} casting shadows)",
+            stats.fps, stats.frame_time * 1000.0,
+            culling.visible, culling.total, culling.shadow_casters
+        );
+
+        // Darken the area behind the text so it stays readable in front of a bright sky
+        let lines = text.lines().count() as f32;
+        batch.rect(
+            [MARGIN, MARGIN],
+            [glyphs.measure(&text) + MARGIN * 2.0, glyphs.line_height() * lines + MARGIN * 2.0],
+            BACKGROUND_COLOR
+        );
+        batch.text(glyphs, &text, [MARGIN * 2.0, MARGIN * 2.0], TEXT_COLOR);
+    }
+
+    // The crosshair's lines are split around the center, so it isn't blended twice
+    let center = [(dimensions[0] / 2) as f32, (dimensions[1] / 2) as f32];
+    let half = CROSSHAIR_THICKNESS / 2.0;
+    let length = CROSSHAIR_SIZE - half;
+    batch.rect([center[0] - CROSSHAIR_SIZE, center[1] - half], [length, CROSSHAIR_THICKNESS], CROSSHAIR_COLOR);
+    batch.rect([center[0] + half, center[1] - half], [length, CROSSHAIR_THICKNESS], CROSSHAIR_COLOR);
+    batch.rect([center[0] - half, center[1] - CROSSHAIR_SIZE], [CROSSHAIR_THICKNESS, CROSSHAIR_SIZE * 2.0], CROSSHAIR_COLOR);
+}
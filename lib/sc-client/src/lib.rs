@@ -1,55 +1,296 @@
 extern crate cgmath;
+extern crate glsl_to_spirv;
+extern crate image;
+extern crate rusttype;
 #[macro_use] extern crate vulkano;
 extern crate vulkano_win;
 extern crate winit;
 extern crate sc_client_game;
 extern crate sc_input_data;
 
+mod bounds;
+mod config;
+mod cursor;
+mod debuglines;
+mod device;
+mod error;
+mod font;
 mod framecounter;
 mod frontend;
-mod teapot;
+mod hud;
+mod material;
+mod mesh;
+mod meshgen;
+mod meshtools;
+mod overlay;
+mod postprocess;
+mod screenshot;
+mod session;
+mod settings;
+mod shaderreload;
+mod shadow;
+mod sky;
 
-mod vs { include!{concat!(env!("OUT_DIR"), "/shaders/src/shader_vs.glsl")} }
-mod fs { include!{concat!(env!("OUT_DIR"), "/shaders/src/shader_fs.glsl")} }
+/// Includes a shader compiled by the build script. A module compiled from the same source at
+/// runtime can stand in for it, as long as its interface matches the build's `SPIRV`.
+macro_rules! shader {
+    ($name:ident, $path:tt) => {
+        mod $name {
+            include!{concat!(env!("OUT_DIR"), "/shaders/src/", $path)}
 
-use sc_client_game::{ClientGame, ClientGameEvent};
-use sc_input_data::Button;
+            #[allow(dead_code)]
+            pub const SPIRV: &'static [u8] =
+                include_bytes!(concat!(env!("OUT_DIR"), "/spirv/src/", $path));
+
+            #[allow(dead_code)]
+            impl Shader {
+                pub unsafe fn from_module(
+                    module: ::std::sync::Arc<::vulkano::pipeline::shader::ShaderModule>
+                ) -> Shader {
+                    Shader { shader: module }
+                }
+            }
+        }
+    }
+}
+
+shader!(vs, "shader_vs.glsl");
+shader!(fs, "shader_fs.glsl");
+shader!(shadow_vs, "shader_shadow_vs.glsl");
+shader!(shadow_fs, "shader_shadow_fs.glsl");
+shader!(sky_vs, "shader_sky_vs.glsl");
+shader!(sky_fs, "shader_sky_fs.glsl");
+shader!(debug_vs, "shader_debug_vs.glsl");
+shader!(debug_fs, "shader_debug_fs.glsl");
+shader!(overlay_vs, "shader_overlay_vs.glsl");
+shader!(overlay_fs, "shader_overlay_fs.glsl");
+shader!(post_vs, "shader_post_vs.glsl");
+shader!(post_bright_fs, "shader_post_bright_fs.glsl");
+shader!(post_blur_fs, "shader_post_blur_fs.glsl");
+shader!(post_tonemap_fs, "shader_post_tonemap_fs.glsl");
+shader!(post_fxaa_fs, "shader_post_fxaa_fs.glsl");
+
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use sc_client_game::{ClientGame, ClientGameEvent, ClientGameCommand};
+use config::Config;
+use device::DeviceSettings;
+use error::FrontendError;
 use framecounter::FrameCounter;
 use frontend::{Frontend, FrontendEvent};
+use hud::HudStats;
+use material::MaterialDesc;
+use session::{SessionRecorder, SessionReplay};
+use settings::GraphicsSettings;
+
+const CONFIG_PATH: &'static str = "client.cfg";
+const MATERIALS_PATH: &'static str = "assets/materials.cfg";
+const MAPS_DIR: &'static str = "assets/maps";
+const SHADER_DIR: &'static str = concat!(env!("CARGO_MANIFEST_DIR"), "/src");
+
+/// Runs the client until it's closed, returning the process exit code.
+pub fn run() -> i32 {
+    match run_client() {
+        Ok(()) => 0,
+        Err(e) => {
+            let _ = writeln!(io::stderr(), "Error: {}", e);
+            1
+        }
+    }
+}
+
+fn run_client() -> Result<(), FrontendError> {
+    // Load the config, command line arguments can override values in it
+    let mut config = Config::load(CONFIG_PATH).unwrap_or_else(|e| {
+        println!("Error in {}: {}, using default settings", CONFIG_PATH, e);
+        Config::new()
+    });
+    let args = config.apply_args(std::env::args().skip(1));
+
+    if args.iter().any(|a| a == "--list-devices") {
+        return device::print_devices();
+    }
+
+    let settings = GraphicsSettings::from_config(&config).unwrap_or_else(|e| {
+        println!("{}, using default graphics settings", e);
+        GraphicsSettings::default()
+    });
+    let device_settings = DeviceSettings::from_config(&config).unwrap_or_else(|e| {
+        println!("{}, using default device settings", e);
+        DeviceSettings::default()
+    });
 
-pub fn run() {
     let mut game = ClientGame::connect();
-    let mut frontend = Frontend::init();
+
+    // Apply the local clock settings, nothing syncs the clock with a server yet
+    {
+        let cycle = game.world_mut().day_cycle_mut();
+        match config.get("world.day_length") {
+            Ok(Some(length)) => cycle.set_day_length(length),
+            Ok(None) => (),
+            Err(e) => println!("{}, using default day length", e),
+        }
+        match config.get("world.time_of_day") {
+            Ok(Some(time)) => cycle.set_time_of_day(time),
+            Ok(None) => (),
+            Err(e) => println!("{}, using default time of day", e),
+        }
+    }
+
+    // Fog and sky come with the map, the test scene is the only one until a server sends maps
+    let map = config.get_or("world.map", "test".to_string()).unwrap_or_else(|e| {
+        println!("{}, loading the test map", e);
+        "test".to_string()
+    });
+    let map_path = format!("{}/{}.cfg", MAPS_DIR, map);
+    match Config::load(&map_path) {
+        Ok(map_config) => {
+            let atmosphere = game.world_mut().atmosphere_mut();
+            match map_config.get("atmosphere.fog_distance") {
+                Ok(Some(distance)) => atmosphere.set_visibility(distance),
+                Ok(None) => (),
+                Err(e) => println!("{}, using default fog distance", e),
+            }
+            match map_config.get("atmosphere.sun_disc") {
+                Ok(Some(sun_disc)) => atmosphere.sun_disc = sun_disc,
+                Ok(None) => (),
+                Err(e) => println!("{}, using default sun disc", e),
+            }
+        },
+        Err(e) => println!("Error in {}: {}, using the default atmosphere", map_path, e),
+    }
+    match config.get("world.pause_unfocused") {
+        Ok(Some(pause)) => game.set_pause_unfocused(pause),
+        Ok(None) => (),
+        Err(e) => println!("{}, not pausing when unfocused", e),
+    }
+    match config.get("debug.draw") {
+        Ok(Some(draw)) => game.world_mut().debug_mut().set_enabled(draw),
+        Ok(None) => (),
+        Err(e) => println!("{}, not drawing debug shapes", e),
+    }
+
+    let materials = Config::load(MATERIALS_PATH)
+        .and_then(|c| MaterialDesc::all_from_config(&c))
+        .unwrap_or_else(|e| {
+            println!("Error in {}: {}, using default materials", MATERIALS_PATH, e);
+            Vec::new()
+        });
+
+    // A session can be recorded while playing, or a recorded one replayed into an image sequence
+    let mut recorder = match config.get::<String>("capture.record") {
+        Ok(Some(path)) => Some(try!(SessionRecorder::create(&path))),
+        Ok(None) => None,
+        Err(e) => { println!("{}, not recording", e); None },
+    };
+    let replay_frames = match config.get::<String>("capture.replay") {
+        Ok(Some(path)) => Some(try!(session::load(&path))),
+        Ok(None) => None,
+        Err(e) => { println!("{}, not replaying", e); None },
+    };
+    let replay_limit = config.get_or("capture.frames", 0usize).unwrap_or_else(|e| {
+        println!("{}, replaying the whole session", e);
+        0
+    });
+    let replay_dir = config.get_or("capture.directory", "frames".to_string()).unwrap_or_else(|e| {
+        println!("{}, writing frames to \"frames\"", e);
+        "frames".to_string()
+    });
+    let mut replay = replay_frames
+        .map(|frames| SessionReplay::new(frames, replay_limit, PathBuf::from(replay_dir)));
+
+    // Replayed frames are only saved, so they're rendered offscreen
+    let offscreen = replay.is_some();
+    let mut frontend = try!(Frontend::init(settings, &device_settings, &materials, offscreen));
+    match config.get::<bool>("debug.reload_shaders") {
+        Ok(Some(true)) => if let Err(e) = frontend.watch_shaders(Path::new(SHADER_DIR)) {
+            println!("{}, not reloading shaders", e);
+        },
+        Ok(_) => (),
+        Err(e) => println!("{}, not reloading shaders", e),
+    }
     let mut counter = FrameCounter::new();
 
     loop {
         // Get the frontend events and handle them or send them over
         let mut should_break = false;
+        let mut take_screenshot = false;
+        let mut events = Vec::new();
         frontend.poll_events(|event| {
             match event {
                 FrontendEvent::Close => should_break = true,
-                FrontendEvent::ButtonState(Button::Menu, _) => should_break = true,
+                FrontendEvent::Screenshot => take_screenshot = true,
                 FrontendEvent::ButtonState(b, s) =>
-                    game.handle_event(ClientGameEvent::ButtonState(b, s)),
+                    events.push(ClientGameEvent::ButtonState(b, s)),
                 FrontendEvent::MouseMove(position, should_track) =>
-                    game.handle_event(ClientGameEvent::MouseMove(position, should_track)),
+                    events.push(ClientGameEvent::MouseMove(position, should_track)),
+                FrontendEvent::Scroll(lines) =>
+                    events.push(ClientGameEvent::Scroll(lines)),
+                FrontendEvent::Text(text) =>
+                    events.push(ClientGameEvent::Text(text)),
+                FrontendEvent::Focus(focused) =>
+                    events.push(ClientGameEvent::Focus(focused)),
             }
         });
         if should_break { break; }
 
+        // While replaying, the recorded input and time steps take the place of the live ones
+        let delta = match replay {
+            Some(ref mut replay) => match replay.next_frame() {
+                Some(frame) => {
+                    events = frame.events;
+                    frame.delta
+                },
+                None => break,
+            },
+            None => counter.delta(),
+        };
+        for event in events {
+            if let Some(ref mut recorder) = recorder {
+                try!(recorder.event(&event));
+            }
+            game.handle_event(event);
+        }
+        if let Some(ref mut recorder) = recorder {
+            try!(recorder.end_frame(delta));
+        }
+
         // Update the backend
-        game.update(counter.delta());
+        game.update(delta);
 
         // Check what the backend wants us to do
-        if let Some(command) = game.next_command() {
+        while let Some(command) = game.next_command() {
             match command {
-                _ => (),
+                ClientGameCommand::Quit => should_break = true,
+                ClientGameCommand::Screenshot => take_screenshot = true,
+                ClientGameCommand::SetCursorMode(mode) => frontend.set_cursor_mode(mode),
+                ClientGameCommand::SetTextInput(enabled) => frontend.set_text_input(enabled),
             }
         }
+        if should_break { break; }
+
+        if take_screenshot {
+            frontend.screenshot();
+        }
 
         // Render the updated game state
-        frontend.render(game.world());
+        let stats = HudStats {
+            fps: counter.fps(),
+            frame_time: counter.frame_time(),
+        };
+        match replay {
+            Some(ref replay) => try!(replay.render(&mut frontend, game.world(), &stats)),
+            None => try!(frontend.render(game.world(), &stats)),
+        }
 
         counter.tick();
     }
+
+    if let Some(recorder) = recorder {
+        try!(recorder.finish());
+    }
+    try!(frontend.finish_captures());
+
+    Ok(())
 }
@@ -0,0 +1,191 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use image;
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
+use vulkano::command_buffer::{self, PrimaryCommandBufferBuilder};
+use vulkano::descriptor::descriptor_set::DescriptorPool;
+use vulkano::device::{Device, Queue};
+use vulkano::format::R8G8B8A8Srgb;
+use vulkano::image::Dimensions;
+use vulkano::image::immutable::ImmutableImage;
+use vulkano::sampler::Sampler;
+
+use config::{Config, ConfigError};
+use error::FrontendError;
+use frontend::pipeline_layout;
+use fs;
+
+/// The name of the material used for anything without a valid material.
+pub const DEFAULT_MATERIAL: &'static str = "default";
+
+pub type Texture = ImmutableImage<R8G8B8A8Srgb>;
+
+/// A material's parameters as described in the materials file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MaterialDesc {
+    pub color: [f32; 4],
+    /// Path to a PNG albedo texture, multiplied with the color.
+    pub texture: Option<String>,
+    /// Between 0.0 for a mirror-like surface and 1.0 for a completely matte one.
+    pub roughness: f32,
+    pub specular: f32,
+}
+
+impl MaterialDesc {
+    /// Reads the material from the keys in the section with the material's name.
+    pub fn from_config(config: &Config, name: &str) -> Result<Self, ConfigError> {
+        let default = MaterialDesc::default();
+        let key = |k: &str| format!("{}.{}", name, k);
+
+        let color = match try!(config.get::<String>(&key("color"))) {
+            Some(value) => try!(parse_color(&value).ok_or(ConfigError::InvalidValue(key("color"), value))),
+            None => default.color,
+        };
+
+        Ok(MaterialDesc {
+            color: color,
+            texture: try!(config.get(&key("texture"))),
+            roughness: try!(config.get_or(&key("roughness"), default.roughness)),
+            specular: try!(config.get_or(&key("specular"), default.specular)),
+        })
+    }
+
+    /// Reads every section in the config as a material.
+    pub fn all_from_config(config: &Config) -> Result<Vec<(String, Self)>, ConfigError> {
+        config.sections().into_iter()
+            .map(|name| MaterialDesc::from_config(config, &name).map(|desc| (name, desc)))
+            .collect()
+    }
+}
+
+impl Default for MaterialDesc {
+    fn default() -> Self {
+        MaterialDesc {
+            color: [1.0, 1.0, 1.0, 1.0],
+            texture: None,
+            roughness: 0.5,
+            specular: 0.5,
+        }
+    }
+}
+
+/// Parses a comma separated RGB or RGBA color.
+fn parse_color(value: &str) -> Option<[f32; 4]> {
+    let parts: Result<Vec<f32>, _> = value.split(',').map(|p| p.trim().parse()).collect();
+    match parts.ok() {
+        Some(ref p) if p.len() == 3 => Some([p[0], p[1], p[2], 1.0]),
+        Some(ref p) if p.len() == 4 => Some([p[0], p[1], p[2], p[3]]),
+        _ => None,
+    }
+}
+
+/// A material uploaded to the GPU, ready to be bound for drawing.
+pub struct Material {
+    set: Arc<pipeline_layout::set1::Set>,
+}
+
+impl Material {
+    pub fn new(
+        device: &Arc<Device>, queue: &Arc<Queue>,
+        pool: &Arc<DescriptorPool>, layout: &Arc<pipeline_layout::CustomPipeline>,
+        desc: &MaterialDesc, texture: &Arc<Texture>, sampler: &Arc<Sampler>
+    ) -> Result<Self, FrontendError> {
+        let params = try!(unsafe {
+            CpuAccessibleBuffer::<fs::ty::Material>::uninitialized(
+                device, &BufferUsage::all(), Some(queue.family())
+            ).map_err(FrontendError::Buffer)
+        });
+
+        {
+            let mut mapping = try!(params.write(Duration::new(0, 0))
+                .map_err(FrontendError::BufferAccess));
+            mapping.base_color = desc.color;
+            mapping.roughness = desc.roughness;
+            mapping.specular = desc.specular;
+        }
+
+        let set = pipeline_layout::set1::Set::new(pool, layout, &pipeline_layout::set1::Descriptors {
+            material: &params,
+            albedo: (sampler, texture),
+        });
+
+        Ok(Material {
+            set: set,
+        })
+    }
+
+    pub fn set(&self) -> &Arc<pipeline_layout::set1::Set> {
+        &self.set
+    }
+}
+
+/// Loads a PNG file into a texture.
+pub fn load_texture(device: &Arc<Device>, queue: &Arc<Queue>, path: &str)
+                    -> Result<Arc<Texture>, FrontendError> {
+    let image = try!(image::open(path)
+        .map_err(|e| FrontendError::Texture(path.to_string(), e)))
+        .to_rgba();
+    let (width, height) = image.dimensions();
+
+    upload_texture(device, queue, width, height, &image.into_raw())
+}
+
+/// Creates a 1x1 texture of a single color, used for materials without a texture.
+pub fn solid_texture(device: &Arc<Device>, queue: &Arc<Queue>, color: [u8; 4])
+                     -> Result<Arc<Texture>, FrontendError> {
+    upload_texture(device, queue, 1, 1, &color)
+}
+
+/// Uploads RGBA pixel data to a new texture, waiting until the upload is done.
+pub fn upload_texture(device: &Arc<Device>, queue: &Arc<Queue>, width: u32, height: u32,
+                      pixels: &[u8]) -> Result<Arc<Texture>, FrontendError> {
+    let texture = try!(ImmutableImage::new(
+        device, Dimensions::Dim2d { width: width, height: height }, R8G8B8A8Srgb,
+        Some(queue.family())
+    ).map_err(FrontendError::Image));
+
+    let staging = try!(CpuAccessibleBuffer::from_iter(
+        device, &BufferUsage::transfer_source(), Some(queue.family()),
+        pixels.chunks(4).map(|p| [p[0], p[1], p[2], p[3]])
+    ).map_err(FrontendError::Buffer));
+
+    let buffer = PrimaryCommandBufferBuilder::new(device, queue.family())
+        .copy_buffer_to_color_image(&staging, &texture, 0, 0..1, [0, 0, 0], [width, height, 1])
+        .build();
+    let submission = try!(command_buffer::submit(&buffer, queue).map_err(FrontendError::Submit));
+    try!(submission.wait(Duration::from_secs(5)).map_err(FrontendError::FrameWait));
+
+    Ok(texture)
+}
+
+#[cfg(test)]
+mod tests {
+    use config::Config;
+    use material::MaterialDesc;
+
+    #[test]
+    fn reads_materials_from_sections() {
+        let config = Config::parse(
+            "[gold]\ncolor = 1.0, 0.8, 0.3\nroughness = 0.2\n\
+             [brick]\ntexture = assets/textures/brick.png\n"
+        ).unwrap();
+
+        let materials = MaterialDesc::all_from_config(&config).unwrap();
+
+        assert_eq!(materials[0].0, "brick");
+        assert_eq!(materials[0].1.texture, Some("assets/textures/brick.png".to_string()));
+        assert_eq!(materials[0].1.color, [1.0, 1.0, 1.0, 1.0]);
+        assert_eq!(materials[1].0, "gold");
+        assert_eq!(materials[1].1.color, [1.0, 0.8, 0.3, 1.0]);
+        assert_eq!(materials[1].1.roughness, 0.2);
+    }
+
+    #[test]
+    fn rejects_invalid_colors() {
+        let config = Config::parse("[gold]\ncolor = 1.0, 0.8\n").unwrap();
+
+        assert!(MaterialDesc::from_config(&config, "gold").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+use std::ops::Range;
+use std::sync::Arc;
+use std::time::Duration;
+
+use cgmath::Vector3;
+use vulkano::buffer::{BufferSlice, BufferUsage, TypedBuffer};
+use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
+use vulkano::buffer::immutable::ImmutableBuffer;
+use vulkano::command_buffer::{self, PrimaryCommandBufferBuilder};
+use vulkano::device::{Device, Queue};
+
+use bounds::Bounds;
+use error::FrontendError;
+use meshtools;
+
+#[derive(Copy, Clone)]
+pub struct Vertex {
+    pub i_position: [f32; 3],
+    pub i_normal: [f32; 3],
+    pub i_uv: [f32; 2],
+    pub i_tangent: [f32; 4], // w is the bitangent's handedness
+}
+
+impl_vertex!(Vertex, i_position, i_normal, i_uv, i_tangent);
+
+/// Per-instance data, read by the vertex shader once for every drawn instance.
+#[derive(Copy, Clone)]
+pub struct Instance {
+    pub i_model: [[f32; 4]; 4],
+}
+
+impl_vertex!(Instance, i_model);
+
+/// Vertices and triangle indices of a mesh, before it is uploaded.
+#[derive(Clone, Default)]
+pub struct MeshData {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn new() -> Self {
+        MeshData::default()
+    }
+}
+
+/// Most vertices a shared block holds, so its indices still fit in 16 bits.
+const BLOCK_VERTICES: usize = u16::MAX as usize + 1;
+
+/// Triangle indices on the GPU, 16 bit whenever all vertices can be reached with them.
+pub enum IndexBuffer {
+    U16(Arc<ImmutableBuffer<[u16]>>),
+    U32(Arc<ImmutableBuffer<[u32]>>),
+}
+
+/// The part of an index buffer belonging to a single mesh.
+pub enum IndexSlice<'a> {
+    U16(BufferSlice<'a, [u16], ImmutableBuffer<[u16]>>),
+    U32(BufferSlice<'a, [u32], ImmutableBuffer<[u32]>>),
+}
+
+/// Device-local vertex and index buffers shared by several meshes.
+pub struct MeshBlock {
+    vertex_buffer: Arc<ImmutableBuffer<[Vertex]>>,
+    index_buffer: IndexBuffer,
+}
+
+/// A mesh uploaded to the GPU, ready to be drawn.
+pub struct Mesh {
+    block: Arc<MeshBlock>,
+    indices: Range<usize>,
+    bounds: Bounds,
+}
+
+impl Mesh {
+    /// The vertex buffer of the whole block, the mesh's indices already point at its own
+    /// vertices in it.
+    pub fn vertex_buffer(&self) -> &Arc<ImmutableBuffer<[Vertex]>> {
+        &self.block.vertex_buffer
+    }
+
+    pub fn indices(&self) -> IndexSlice {
+        let range = self.indices.clone();
+        match self.block.index_buffer {
+            IndexBuffer::U16(ref buffer) => IndexSlice::U16(BufferSlice::from(buffer).slice(range).unwrap()),
+            IndexBuffer::U32(ref buffer) => IndexSlice::U32(BufferSlice::from(buffer).slice(range).unwrap()),
+        }
+    }
+
+    /// The mesh's bounding volumes in model space.
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+}
+
+/// Collects static meshes into a few large blocks, which are then uploaded to device-local memory
+/// together. Meshes are placed in the first block with enough vertices left, and meshes too
+/// large for a block get one of their own with 32 bit indices.
+pub struct MeshPool {
+    blocks: Vec<MeshData>,
+    meshes: Vec<(usize, Range<usize>, Bounds)>, // block, indices in the block
+}
+
+impl MeshPool {
+    pub fn new() -> Self {
+        MeshPool {
+            blocks: Vec::new(),
+            meshes: Vec::new(),
+        }
+    }
+
+    /// Adds a mesh to be uploaded, its index in the meshes returned by `upload`.
+    pub fn add(&mut self, data: &MeshData) -> Result<usize, FrontendError> {
+        let positions: Vec<_> = data.vertices.iter().map(|v| Vector3::from(v.i_position)).collect();
+        let bounds = try!(Bounds::from_points(&positions).ok_or(FrontendError::EmptyMesh));
+        try!(meshtools::validate(data));
+
+        let fits = |block: &MeshData| block.vertices.len() + data.vertices.len() <= BLOCK_VERTICES;
+        let block_index = match self.blocks.iter().position(|b| fits(b)) {
+            Some(index) => index,
+            None => {
+                self.blocks.push(MeshData::new());
+                self.blocks.len() - 1
+            },
+        };
+        let block = &mut self.blocks[block_index];
+
+        // Vertex buffers are always bound from their start, so the indices are moved instead
+        let base = block.vertices.len() as u32;
+        let start = block.indices.len();
+        block.vertices.extend_from_slice(&data.vertices);
+        block.indices.extend(data.indices.iter().map(|&i| base + i));
+
+        self.meshes.push((block_index, start..block.indices.len(), bounds));
+        Ok(self.meshes.len() - 1)
+    }
+
+    /// Copies all blocks into device-local buffers, returning the meshes in the order they were
+    /// added.
+    pub fn upload(self, device: &Arc<Device>, queue: &Arc<Queue>) -> Result<Vec<Mesh>, FrontendError> {
+        let mut blocks = Vec::with_capacity(self.blocks.len());
+        for data in &self.blocks {
+            // Small blocks only need half the index memory
+            let index_buffer = if data.vertices.len() <= BLOCK_VERTICES {
+                let indices: Vec<u16> = data.indices.iter().map(|&i| i as u16).collect();
+                IndexBuffer::U16(try!(upload_static(device, queue, &indices)))
+            } else {
+                IndexBuffer::U32(try!(upload_static(device, queue, &data.indices)))
+            };
+
+            blocks.push(Arc::new(MeshBlock {
+                vertex_buffer: try!(upload_static(device, queue, &data.vertices)),
+                index_buffer: index_buffer,
+            }));
+        }
+
+        Ok(self.meshes.into_iter().map(|(block, indices, bounds)| Mesh {
+            block: blocks[block].clone(),
+            indices: indices,
+            bounds: bounds,
+        }).collect())
+    }
+}
+
+/// Uploads data that never changes to a device-local buffer, by copying it over from a staging
+/// buffer.
+pub fn upload_static<T>(device: &Arc<Device>, queue: &Arc<Queue>, data: &[T])
+                        -> Result<Arc<ImmutableBuffer<[T]>>, FrontendError>
+    where T: Copy + Send + Sync + 'static
+{
+    let staging = try!(CpuAccessibleBuffer::from_iter(
+        device, &BufferUsage::transfer_source(), Some(queue.family()), data.iter().cloned()
+    ).map_err(FrontendError::Buffer));
+
+    let usage = BufferUsage {
+        vertex_buffer: true,
+        index_buffer: true,
+        transfer_dest: true,
+        .. BufferUsage::none()
+    };
+    let buffer = try!(ImmutableBuffer::array(device, data.len(), &usage, Some(queue.family()))
+        .map_err(FrontendError::Buffer));
+
+    let command_buffer = PrimaryCommandBufferBuilder::new(device, queue.family())
+        .copy_buffer(&staging, &buffer)
+        .build();
+    let submission = try!(command_buffer::submit(&command_buffer, queue).map_err(FrontendError::Submit));
+    try!(submission.wait(Duration::from_secs(5)).map_err(FrontendError::FrameWait));
+
+    Ok(buffer)
+}
+
+/// Creates a buffer the CPU can keep writing to, for data that changes every frame.
+pub fn upload<T>(device: &Arc<Device>, queue: &Arc<Queue>, data: &[T])
+                 -> Result<Arc<CpuAccessibleBuffer<[T]>>, FrontendError>
+    where T: Copy + 'static
+{
+    CpuAccessibleBuffer::from_iter(device, &BufferUsage::all(), Some(queue.family()), data.iter().cloned())
+        .map_err(FrontendError::Buffer)
+}
+
+/// Overwrites the start of the buffer with the data, the buffer must not be in use by the GPU.
+pub fn write<T: Copy + 'static>(buffer: &CpuAccessibleBuffer<[T]>, data: &[T])
+                                -> Result<(), FrontendError> {
+    let mut mapping = try!(buffer.write(Duration::new(0, 0))
+        .map_err(FrontendError::BufferAccess));
+    for (o, i) in mapping.iter_mut().zip(data.iter()) {
+        *o = *i;
+    }
+
+    Ok(())
+}
+
+/// Writes the data to the buffer, replacing the buffer if its length doesn't match. Empty data
+/// leaves no buffer at all, since empty buffers can't be created.
+pub fn write_or_upload<T: Copy + 'static>(device: &Arc<Device>, queue: &Arc<Queue>,
+                                          buffer: &mut Option<Arc<CpuAccessibleBuffer<[T]>>>,
+                                          data: &[T]) -> Result<(), FrontendError> {
+    if data.is_empty() {
+        *buffer = None;
+        return Ok(());
+    }
+
+    let reusable = buffer.as_ref().map(|b| b.len() == data.len()).unwrap_or(false);
+    if reusable {
+        try!(write(buffer.as_ref().unwrap(), data));
+    } else {
+        *buffer = Some(try!(upload(device, queue, data)));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use mesh::{MeshData, MeshPool};
+    use meshgen;
+
+    #[test]
+    fn pool_packs_meshes_into_blocks() {
+        let mut pool = MeshPool::new();
+        let cube = meshgen::cube(1.0);
+        let plane = meshgen::plane(1.0, 200);
+        let large = meshgen::plane(1.0, 300);
+
+        assert_eq!(pool.add(&cube).unwrap(), 0);
+        assert_eq!(pool.add(&plane).unwrap(), 1);
+        // Doesn't fit next to the first plane anymore, and too large for any block
+        pool.add(&plane).unwrap();
+        pool.add(&large).unwrap();
+        assert!(pool.add(&MeshData::new()).is_err());
+
+        let blocks: Vec<_> = pool.meshes.iter().map(|m| m.0).collect();
+        assert_eq!(blocks, vec![0, 0, 1, 2]);
+        assert_eq!(pool.blocks[0].vertices.len(), cube.vertices.len() + plane.vertices.len());
+
+        // The second mesh's indices now point past the first mesh's vertices
+        let (_, ref range, _) = pool.meshes[1];
+        let first = pool.blocks[0].indices[range.start];
+        assert_eq!(first, plane.indices[0] + cube.vertices.len() as u32);
+        assert_eq!(range.len(), plane.indices.len());
+    }
+}
@@ -0,0 +1,339 @@
+use std::f32::consts::PI;
+
+use cgmath::{Vector3, InnerSpace, Zero};
+
+use mesh::{MeshData, Vertex};
+
+/// Control point indices of the teapot's Bezier patches, 4 rows of 4 points each.
+///
+/// The teapot is symmetric, so only the patches for one side are stored. The rim, body, lid and
+/// bottom are mirrored into all four quadrants, the handle and spout only across the XZ plane.
+const TEAPOT_PATCHES: [[usize; 16]; 10] = [
+    // Rim
+    [102, 103, 104, 105, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    // Body
+    [12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23, 24, 25, 26, 27],
+    [24, 25, 26, 27, 29, 30, 31, 32, 33, 34, 35, 36, 37, 38, 39, 40],
+    // Lid
+    [96, 96, 96, 96, 97, 98, 99, 100, 101, 101, 101, 101, 0, 1, 2, 3],
+    [0, 1, 2, 3, 106, 107, 108, 109, 110, 111, 112, 113, 114, 115, 116, 117],
+    // Bottom
+    [118, 118, 118, 118, 124, 122, 119, 121, 123, 126, 125, 120, 40, 39, 38, 37],
+    // Handle
+    [41, 42, 43, 44, 45, 46, 47, 48, 49, 50, 51, 52, 53, 54, 55, 56],
+    [53, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 28, 65, 66, 67],
+    // Spout
+    [68, 69, 70, 71, 72, 73, 74, 75, 76, 77, 78, 79, 80, 81, 82, 83],
+    [80, 81, 82, 83, 84, 85, 86, 87, 88, 89, 90, 91, 92, 93, 94, 95],
+];
+
+/// The patches in `TEAPOT_PATCHES` that are mirrored into all four quadrants.
+const TEAPOT_FULLY_MIRRORED: usize = 6;
+
+/// Control points of the teapot's patches, with Z up and the bottom at 0.0.
+const TEAPOT_POINTS: [[f32; 3]; 127] = [
+    [0.2, 0.0, 2.7], [0.2, -0.112, 2.7], [0.112, -0.2, 2.7], [0.0, -0.2, 2.7],
+    [1.3375, 0.0, 2.53125], [1.3375, -0.749, 2.53125], [0.749, -1.3375, 2.53125], [0.0, -1.3375, 2.53125],
+    [1.4375, 0.0, 2.53125], [1.4375, -0.805, 2.53125], [0.805, -1.4375, 2.53125], [0.0, -1.4375, 2.53125],
+    [1.5, 0.0, 2.4], [1.5, -0.84, 2.4], [0.84, -1.5, 2.4], [0.0, -1.5, 2.4],
+    [1.75, 0.0, 1.875], [1.75, -0.98, 1.875], [0.98, -1.75, 1.875], [0.0, -1.75, 1.875],
+    [2.0, 0.0, 1.35], [2.0, -1.12, 1.35], [1.12, -2.0, 1.35], [0.0, -2.0, 1.35],
+    [2.0, 0.0, 0.9], [2.0, -1.12, 0.9], [1.12, -2.0, 0.9], [0.0, -2.0, 0.9],
+    [-2.0, 0.0, 0.9], [2.0, 0.0, 0.45], [2.0, -1.12, 0.45], [1.12, -2.0, 0.45],
+    [0.0, -2.0, 0.45], [1.5, 0.0, 0.225], [1.5, -0.84, 0.225], [0.84, -1.5, 0.225],
+    [0.0, -1.5, 0.225], [1.5, 0.0, 0.15], [1.5, -0.84, 0.15], [0.84, -1.5, 0.15],
+    [0.0, -1.5, 0.15], [-1.6, 0.0, 2.025], [-1.6, -0.3, 2.025], [-1.5, -0.3, 2.25],
+    [-1.5, 0.0, 2.25], [-2.3, 0.0, 2.025], [-2.3, -0.3, 2.025], [-2.5, -0.3, 2.25],
+    [-2.5, 0.0, 2.25], [-2.7, 0.0, 2.025], [-2.7, -0.3, 2.025], [-3.0, -0.3, 2.25],
+    [-3.0, 0.0, 2.25], [-2.7, 0.0, 1.8], [-2.7, -0.3, 1.8], [-3.0, -0.3, 1.8],
+    [-3.0, 0.0, 1.8], [-2.7, 0.0, 1.575], [-2.7, -0.3, 1.575], [-3.0, -0.3, 1.35],
+    [-3.0, 0.0, 1.35], [-2.5, 0.0, 1.125], [-2.5, -0.3, 1.125], [-2.65, -0.3, 0.9375],
+    [-2.65, 0.0, 0.9375], [-2.0, -0.3, 0.9], [-1.9, -0.3, 0.6], [-1.9, 0.0, 0.6],
+    [1.7, 0.0, 1.425], [1.7, -0.66, 1.425], [1.7, -0.66, 0.6], [1.7, 0.0, 0.6],
+    [2.6, 0.0, 1.425], [2.6, -0.66, 1.425], [3.1, -0.66, 0.825], [3.1, 0.0, 0.825],
+    [2.3, 0.0, 2.1], [2.3, -0.25, 2.1], [2.4, -0.25, 2.025], [2.4, 0.0, 2.025],
+    [2.7, 0.0, 2.4], [2.7, -0.25, 2.4], [3.3, -0.25, 2.4], [3.3, 0.0, 2.4],
+    [2.8, 0.0, 2.475], [2.8, -0.25, 2.475], [3.525, -0.25, 2.49375], [3.525, 0.0, 2.49375],
+    [2.9, 0.0, 2.475], [2.9, -0.15, 2.475], [3.45, -0.15, 2.5125], [3.45, 0.0, 2.5125],
+    [2.8, 0.0, 2.4], [2.8, -0.15, 2.4], [3.2, -0.15, 2.4], [3.2, 0.0, 2.4],
+    [0.0, 0.0, 3.15], [0.8, 0.0, 3.15], [0.8, -0.45, 3.15], [0.45, -0.8, 3.15],
+    [0.0, -0.8, 3.15], [0.0, 0.0, 2.85], [1.4, 0.0, 2.4], [1.4, -0.784, 2.4],
+    [0.784, -1.4, 2.4], [0.0, -1.4, 2.4], [0.4, 0.0, 2.55], [0.4, -0.224, 2.55],
+    [0.224, -0.4, 2.55], [0.0, -0.4, 2.55], [1.3, 0.0, 2.55], [1.3, -0.728, 2.55],
+    [0.728, -1.3, 2.55], [0.0, -1.3, 2.55], [1.3, 0.0, 2.4], [1.3, -0.728, 2.4],
+    [0.728, -1.3, 2.4], [0.0, -1.3, 2.4], [0.0, 0.0, 0.0], [1.425, -0.798, 0.0],
+    [1.5, 0.0, 0.075], [1.425, 0.0, 0.0], [0.798, -1.425, 0.0], [0.0, -1.5, 0.075],
+    [0.0, -1.425, 0.0], [1.5, -0.84, 0.075], [0.84, -1.5, 0.075],
+];
+
+/// Tessellates the Utah teapot, splitting every Bezier patch into subdivisions by subdivisions
+/// quads. The teapot is 3.15 units high with its bottom at the origin and Y up.
+pub fn teapot(subdivisions: u32) -> MeshData {
+    let mut data = MeshData::new();
+
+    for (i, patch) in TEAPOT_PATCHES.iter().enumerate() {
+        let point = |row: usize, column: usize| {
+            let p = TEAPOT_POINTS[patch[row * 4 + column]];
+            Vector3::new(p[0], p[1], p[2])
+        };
+
+        // Mirrored patches are stored with their columns reversed, so every copy is wound the same
+        let mut mirrors = vec![(1.0, 1.0, false), (1.0, -1.0, true)];
+        if i < TEAPOT_FULLY_MIRRORED {
+            mirrors.push((-1.0, 1.0, true));
+            mirrors.push((-1.0, -1.0, false));
+        }
+
+        for &(x, y, reverse) in &mirrors {
+            let mut points = [[Vector3::zero(); 4]; 4];
+            for row in 0..4 {
+                for column in 0..4 {
+                    let p = point(row, if reverse { 3 - column } else { column });
+                    points[row][column] = Vector3::new(p.x * x, p.y * y, p.z);
+                }
+            }
+
+            push_grid(&mut data, subdivisions, subdivisions, |u, v| {
+                let (position, normal) = bezier_patch(&points, u, v);
+                // Turn the patch data's Z up into Y up
+                (Vector3::new(position.x, position.z, -position.y),
+                 Vector3::new(normal.x, normal.z, -normal.y),
+                 [v, u])
+            });
+        }
+    }
+
+    data
+}
+
+/// Evaluates the position and outward normal of a bicubic Bezier patch.
+fn bezier_patch(points: &[[Vector3<f32>; 4]; 4], u: f32, v: f32) -> (Vector3<f32>, Vector3<f32>) {
+    let evaluate = |bu: [f32; 4], bv: [f32; 4]| {
+        let mut sum = Vector3::zero();
+        for row in 0..4 {
+            for column in 0..4 {
+                sum = sum + points[row][column] * (bu[row] * bv[column]);
+            }
+        }
+        sum
+    };
+
+    let position = evaluate(bernstein(u), bernstein(v));
+    let normal = |u| evaluate(bernstein(u), bernstein_derivative(v))
+        .cross(evaluate(bernstein_derivative(u), bernstein(v)));
+
+    // Where a row of control points collapses into a single point, like the top of the lid, the
+    // derivative along it is zero, so take the normal from just next to it instead
+    let mut n = normal(u);
+    if n.magnitude2() < 1.0e-12 {
+        n = normal(if u < 0.5 { u + 1.0e-3 } else { u - 1.0e-3 });
+    }
+
+    (position, n.normalize())
+}
+
+fn bernstein(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [s * s * s, 3.0 * t * s * s, 3.0 * t * t * s, t * t * t]
+}
+
+fn bernstein_derivative(t: f32) -> [f32; 4] {
+    let s = 1.0 - t;
+    [-3.0 * s * s, 3.0 * s * s - 6.0 * t * s, 6.0 * t * s - 3.0 * t * t, 3.0 * t * t]
+}
+
+/// An axis-aligned cube centered on the origin, with separate vertices for every face.
+pub fn cube(size: f32) -> MeshData {
+    let mut data = MeshData::new();
+    let half = size * 0.5;
+
+    // Every face is spanned by two axes, perpendicular to its normal
+    let axes = [Vector3::unit_x(), Vector3::unit_y(), Vector3::unit_z()];
+    for i in 0..3 {
+        for &sign in &[1.0, -1.0] {
+            let normal = axes[i] * sign;
+            let a = axes[(i + 1) % 3];
+            let b = axes[(i + 2) % 3];
+
+            push_grid(&mut data, 1, 1, |u, v| {
+                let position = (normal + a * (u * 2.0 - 1.0) + b * (v * 2.0 - 1.0)) * half;
+                (position, normal, [u, v])
+            });
+        }
+    }
+
+    data
+}
+
+/// A square in the XZ plane centered on the origin and facing up, split into subdivisions by
+/// subdivisions quads.
+pub fn plane(size: f32, subdivisions: u32) -> MeshData {
+    let mut data = MeshData::new();
+
+    push_grid(&mut data, subdivisions, subdivisions, |u, v| {
+        let position = Vector3::new(u - 0.5, 0.0, v - 0.5) * size;
+        (position, Vector3::unit_y(), [u, v])
+    });
+
+    data
+}
+
+/// A sphere centered on the origin, made of segments around the Y axis and rings from pole to
+/// pole.
+pub fn sphere(radius: f32, segments: u32, rings: u32) -> MeshData {
+    capsule(radius, 0.0, segments, rings)
+}
+
+/// A cylinder along the Y axis capped with half spheres, centered on the origin. The height is
+/// the cylinder's height, not including the caps.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> MeshData {
+    let mut data = MeshData::new();
+    let half_rings = (rings / 2).max(1);
+    let total_height = height + radius * 2.0;
+
+    // Both caps are a half sphere moved away from the center, the cylinder is the band between
+    for &(offset, start) in &[(height * 0.5, 0.0), (height * -0.5, PI * 0.5)] {
+        push_grid(&mut data, segments, half_rings, |u, v| {
+            let (sin_azimuth, cos_azimuth) = (u * 2.0 * PI).sin_cos();
+            let (sin_polar, cos_polar) = (start + v * PI * 0.5).sin_cos();
+
+            let normal = Vector3::new(sin_polar * cos_azimuth, cos_polar, sin_polar * sin_azimuth);
+            let position = normal * radius + Vector3::new(0.0, offset, 0.0);
+            (position, normal, [u, 0.5 - position.y / total_height])
+        });
+
+        if offset > 0.0 {
+            push_cylinder_band(&mut data, segments);
+        }
+    }
+
+    data
+}
+
+/// Connects the last ring of the top cap with the first ring of the bottom cap, both pushed by
+/// `capsule` with the same amount of segments.
+fn push_cylinder_band(data: &mut MeshData, segments: u32) {
+    let top_ring = data.vertices.len() as u32 - (segments + 1);
+    let band = data.vertices.len() as u32;
+
+    // The bottom cap isn't there yet, but starts right after the top cap
+    for i in 0..segments {
+        let (a, b) = (top_ring + i, top_ring + i + 1);
+        let (c, d) = (band + i, band + i + 1);
+        data.indices.extend_from_slice(&[a, c, b, b, c, d]);
+    }
+}
+
+/// Adds a grid of vertices from a function mapping grid coordinates from 0.0 to 1.0 to a
+/// position, normal and texture coordinates, connected by two triangles per cell. Tangents are
+/// left empty for `meshtools::generate_tangents`.
+///
+/// Triangles are wound clockwise as seen from the side the normals face, which is the front face
+/// of the main pipeline. Triangles without any area, like at the poles of a sphere, are skipped.
+fn push_grid<F>(data: &mut MeshData, columns: u32, rows: u32, function: F)
+    where F: Fn(f32, f32) -> (Vector3<f32>, Vector3<f32>, [f32; 2])
+{
+    let columns = columns.max(1);
+    let rows = rows.max(1);
+    let start = data.vertices.len() as u32;
+
+    for row in 0..rows + 1 {
+        for column in 0..columns + 1 {
+            let (position, normal, uv) = function(column as f32 / columns as f32, row as f32 / rows as f32);
+            data.vertices.push(Vertex {
+                i_position: position.into(),
+                i_normal: normal.into(),
+                i_uv: uv,
+                i_tangent: [0.0; 4],
+            });
+        }
+    }
+
+    let index = |column: u32, row: u32| start + row * (columns + 1) + column;
+    for row in 0..rows {
+        for column in 0..columns {
+            let (a, b) = (index(column, row), index(column + 1, row));
+            let (c, d) = (index(column, row + 1), index(column + 1, row + 1));
+            push_triangle(data, a, b, c);
+            push_triangle(data, b, d, c);
+        }
+    }
+}
+
+/// Adds a triangle, ordering its vertices so it's wound clockwise when seen from the side its
+/// vertex normals face.
+fn push_triangle(data: &mut MeshData, a: u32, b: u32, c: u32) {
+    let (face, normal) = {
+        let vertex = |i: u32| {
+            let v = &data.vertices[i as usize];
+            (Vector3::from(v.i_position), Vector3::from(v.i_normal))
+        };
+        let ((pa, na), (pb, nb), (pc, nc)) = (vertex(a), vertex(b), vertex(c));
+        ((pb - pa).cross(pc - pa), na + nb + nc)
+    };
+
+    if face.magnitude2() < 1.0e-12 {
+        return;
+    }
+
+    // The cross product follows the counter-clockwise winding, so reverse if it faces outwards
+    if face.dot(normal) > 0.0 {
+        data.indices.extend_from_slice(&[a, c, b]);
+    } else {
+        data.indices.extend_from_slice(&[a, b, c]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Vector3, InnerSpace};
+    use mesh::MeshData;
+    use meshgen;
+
+    /// Checks that indices are in range and every triangle is clockwise as seen from outside.
+    fn assert_valid(data: &MeshData) {
+        assert!(data.indices.len() % 3 == 0);
+        for triangle in data.indices.chunks(3) {
+            let vertices: Vec<_> = triangle.iter().map(|&i| &data.vertices[i as usize]).collect();
+            let p: Vec<_> = vertices.iter().map(|v| Vector3::from(v.i_position)).collect();
+            let n = vertices.iter().fold(Vector3::new(0.0, 0.0, 0.0), |a, v| a + Vector3::from(v.i_normal));
+            assert!((p[1] - p[0]).cross(p[2] - p[0]).dot(n) < 0.0);
+        }
+        for vertex in &data.vertices {
+            assert!((Vector3::from(vertex.i_normal).magnitude() - 1.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn teapot_has_a_grid_per_patch() {
+        let data = meshgen::teapot(4);
+        assert_eq!(data.vertices.len(), 32 * 5 * 5);
+        assert_eq!(data.indices.iter().cloned().min(), Some(0));
+        assert_valid(&data);
+
+        // The teapot stands on the origin, and its lid's knob is the highest point
+        let top = data.vertices.iter().map(|v| v.i_position[1]).fold(0.0, f32::max);
+        let bottom = data.vertices.iter().map(|v| v.i_position[1]).fold(1.0, f32::min);
+        assert!((top - 3.15).abs() < 0.001 && bottom.abs() < 0.001);
+    }
+
+    #[test]
+    fn shapes_are_valid() {
+        let cube = meshgen::cube(2.0);
+        assert_eq!(cube.indices.len(), 6 * 2 * 3);
+        assert!(cube.vertices.iter().all(|v| v.i_position.iter().all(|c| c.abs() == 1.0)));
+
+        assert_valid(&cube);
+        assert_valid(&meshgen::plane(1.0, 4));
+        assert_valid(&meshgen::sphere(1.0, 16, 8));
+        assert_valid(&meshgen::capsule(0.5, 1.0, 16, 8));
+    }
+
+    #[test]
+    fn capsule_spans_its_height() {
+        let data = meshgen::capsule(0.5, 1.0, 8, 4);
+        let heights: Vec<_> = data.vertices.iter().map(|v| v.i_position[1]).collect();
+        assert!((heights.iter().cloned().fold(0.0, f32::max) - 1.0).abs() < 0.001);
+        assert!((heights.iter().cloned().fold(0.0, f32::min) + 1.0).abs() < 0.001);
+    }
+}
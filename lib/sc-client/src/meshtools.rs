@@ -0,0 +1,407 @@
+use std::collections::HashMap;
+
+use cgmath::{Vector3, InnerSpace, Zero};
+
+use error::FrontendError;
+use mesh::{MeshData, Vertex};
+
+/// Amount of recently used vertices the cache optimization assumes the GPU keeps around.
+const CACHE_SIZE: usize = 32;
+
+/// Distance below which vertex attributes are considered equal.
+const TOLERANCE: f32 = 1.0e-5;
+
+/// Checks that the indices form whole triangles and only refer to existing vertices.
+pub fn validate(data: &MeshData) -> Result<(), FrontendError> {
+    if data.indices.len() % 3 != 0 {
+        return Err(FrontendError::IncompleteTriangle(data.indices.len()));
+    }
+
+    match data.indices.iter().find(|&&i| i as usize >= data.vertices.len()) {
+        Some(&index) => Err(FrontendError::IndexOutOfRange(index, data.vertices.len())),
+        None => Ok(()),
+    }
+}
+
+/// Runs everything a mesh needs before it's drawn: generates normals if it has none, merges
+/// duplicate vertices, generates tangents and reorders the triangles for the vertex cache.
+pub fn prepare(data: &mut MeshData) {
+    if data.vertices.iter().all(|v| v.i_normal == [0.0; 3]) {
+        smooth_normals(data);
+    }
+    weld(data, TOLERANCE);
+    generate_tangents(data);
+    optimize_vertex_cache(data);
+}
+
+/// Calculates the normal of a clockwise wound triangle, its length is twice the triangle's area.
+fn face_normal(data: &MeshData, triangle: &[u32]) -> Vector3<f32> {
+    let position = |i: u32| Vector3::from(data.vertices[i as usize].i_position);
+    let (a, b, c) = (position(triangle[0]), position(triangle[1]), position(triangle[2]));
+    (c - a).cross(b - a)
+}
+
+/// Replaces the normals with the average of the surrounding triangles' normals, weighted by their
+/// area. Vertices at the same position are smoothed together even if they aren't shared, so
+/// texture seams don't show up in the lighting.
+pub fn smooth_normals(data: &mut MeshData) {
+    let mut sums: HashMap<[i64; 3], Vector3<f32>> = HashMap::new();
+    let key = |v: &Vertex| [
+        quantize(v.i_position[0], TOLERANCE),
+        quantize(v.i_position[1], TOLERANCE),
+        quantize(v.i_position[2], TOLERANCE),
+    ];
+
+    for triangle in data.indices.chunks(3) {
+        let normal = face_normal(data, triangle);
+        for &i in triangle {
+            let sum = sums.entry(key(&data.vertices[i as usize])).or_insert_with(Vector3::zero);
+            *sum = *sum + normal;
+        }
+    }
+
+    // Vertices without any triangles with an area keep the normal they had
+    for vertex in &mut data.vertices {
+        if let Some(sum) = sums.get(&key(vertex)) {
+            if sum.magnitude2() > 0.0 {
+                vertex.i_normal = sum.normalize().into();
+            }
+        }
+    }
+}
+
+/// Calculates the tangents along the U texture coordinate, with the handedness of the bitangent
+/// along V in the tangent's W. Vertices without a usable texture mapping get any tangent
+/// perpendicular to their normal.
+pub fn generate_tangents(data: &mut MeshData) {
+    let mut tangents = vec![Vector3::zero(); data.vertices.len()];
+    let mut bitangents = vec![Vector3::zero(); data.vertices.len()];
+
+    for triangle in data.indices.chunks(3) {
+        let vertex = |i: usize| {
+            let v = &data.vertices[triangle[i] as usize];
+            (Vector3::from(v.i_position), v.i_uv)
+        };
+        let ((p0, uv0), (p1, uv1), (p2, uv2)) = (vertex(0), vertex(1), vertex(2));
+
+        let (e1, e2) = (p1 - p0, p2 - p0);
+        let (du1, dv1) = (uv1[0] - uv0[0], uv1[1] - uv0[1]);
+        let (du2, dv2) = (uv2[0] - uv0[0], uv2[1] - uv0[1]);
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() < 1.0e-12 {
+            continue;
+        }
+
+        let tangent = (e1 * dv2 - e2 * dv1) / determinant;
+        let bitangent = (e2 * du1 - e1 * du2) / determinant;
+        for &i in triangle {
+            tangents[i as usize] = tangents[i as usize] + tangent;
+            bitangents[i as usize] = bitangents[i as usize] + bitangent;
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in data.vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let normal = Vector3::from(vertex.i_normal);
+
+        // Make the tangent perpendicular to the normal, or pick one if there's nothing to go on
+        let mut tangent = tangent - normal * normal.dot(tangent);
+        if tangent.magnitude2() < 1.0e-12 {
+            let axis = if normal.x.abs() < 0.9 { Vector3::unit_x() } else { Vector3::unit_y() };
+            tangent = axis - normal * normal.dot(axis);
+        }
+        let tangent = tangent.normalize();
+
+        let handedness = if normal.cross(tangent).dot(bitangent) < 0.0 { -1.0 } else { 1.0 };
+        vertex.i_tangent = [tangent.x, tangent.y, tangent.z, handedness];
+    }
+}
+
+fn quantize(value: f32, tolerance: f32) -> i64 {
+    (value / tolerance).round() as i64
+}
+
+/// Merges vertices with all attributes equal when rounded to the tolerance, removing triangles
+/// that collapse because of it. The first of the merged vertices is kept.
+pub fn weld(data: &mut MeshData, tolerance: f32) {
+    let key = |v: &Vertex| {
+        let mut key = [0i64; 12];
+        let attributes = v.i_position.iter().chain(v.i_normal.iter()).chain(v.i_uv.iter())
+            .chain(v.i_tangent.iter());
+        for (k, &value) in key.iter_mut().zip(attributes) {
+            *k = quantize(value, tolerance);
+        }
+        key
+    };
+
+    let mut welded = HashMap::new();
+    let mut vertices = Vec::new();
+    let remap: Vec<u32> = data.vertices.iter().map(|vertex| {
+        *welded.entry(key(vertex)).or_insert_with(|| {
+            vertices.push(*vertex);
+            vertices.len() as u32 - 1
+        })
+    }).collect();
+
+    let mut indices = Vec::with_capacity(data.indices.len());
+    for triangle in data.indices.chunks(3) {
+        let (a, b, c) = (remap[triangle[0] as usize], remap[triangle[1] as usize], remap[triangle[2] as usize]);
+        if a != b && b != c && c != a {
+            indices.extend_from_slice(&[a, b, c]);
+        }
+    }
+
+    data.vertices = vertices;
+    data.indices = indices;
+}
+
+/// How much drawing a triangle with this vertex next is worth, from Tom Forsyth's "Linear-Speed
+/// Vertex Cache Optimisation". Vertices that are still in the cache, or only have a few triangles
+/// left, are preferred.
+fn vertex_score(cache_position: Option<usize>, remaining: u32) -> f32 {
+    if remaining == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        // The last triangle's vertices are equally good, since the order within it is fixed
+        Some(position) if position < 3 => 0.75,
+        Some(position) => (1.0 - (position - 3) as f32 / (CACHE_SIZE - 3) as f32).powf(1.5),
+        None => 0.0,
+    };
+
+    cache_score + 2.0 * (remaining as f32).powf(-0.5)
+}
+
+/// Reorders the triangles so vertices are reused while they're still in the GPU's vertex cache,
+/// and then the vertices in the order they're first used. Vertices without triangles are removed.
+pub fn optimize_vertex_cache(data: &mut MeshData) {
+    let indices = data.indices.clone();
+    let triangle_count = indices.len() / 3;
+
+    let mut triangles_of = vec![Vec::new(); data.vertices.len()];
+    for (triangle, corners) in indices.chunks(3).enumerate() {
+        for &i in corners {
+            triangles_of[i as usize].push(triangle);
+        }
+    }
+    let mut remaining: Vec<u32> = triangles_of.iter().map(|t| t.len() as u32).collect();
+    let mut scores: Vec<f32> = remaining.iter().map(|&r| vertex_score(None, r)).collect();
+    let mut emitted = vec![false; triangle_count];
+
+    let mut cache: Vec<u32> = Vec::with_capacity(CACHE_SIZE + 3);
+    let mut ordered = Vec::with_capacity(indices.len());
+    let mut next_unemitted = 0;
+    let mut best = None;
+
+    for _ in 0..triangle_count {
+        // When nothing in the cache has triangles left, continue anywhere
+        let triangle = match best {
+            Some(triangle) => triangle,
+            None => {
+                while emitted[next_unemitted] {
+                    next_unemitted += 1;
+                }
+                next_unemitted
+            },
+        };
+        let corners = &indices[triangle * 3..triangle * 3 + 3];
+        emitted[triangle] = true;
+        ordered.extend_from_slice(corners);
+
+        // The triangle's vertices move to the front of the cache, pushing the oldest ones out
+        let mut new_cache = Vec::with_capacity(CACHE_SIZE + 3);
+        for &i in corners.iter().chain(cache.iter()) {
+            if !new_cache.contains(&i) {
+                new_cache.push(i);
+            }
+        }
+        for &i in corners {
+            remaining[i as usize] -= 1;
+        }
+        for (position, &i) in new_cache.iter().enumerate() {
+            let position = if position < CACHE_SIZE { Some(position) } else { None };
+            scores[i as usize] = vertex_score(position, remaining[i as usize]);
+        }
+        new_cache.truncate(CACHE_SIZE);
+        cache = new_cache;
+
+        // Only triangles using a cached vertex changed their score, so the best is among those
+        best = None;
+        let mut best_score = -1.0;
+        for &i in &cache {
+            for &candidate in &triangles_of[i as usize] {
+                if emitted[candidate] {
+                    continue;
+                }
+
+                let score: f32 = indices[candidate * 3..candidate * 3 + 3].iter()
+                    .map(|&i| scores[i as usize])
+                    .sum();
+                if score > best_score {
+                    best = Some(candidate);
+                    best_score = score;
+                }
+            }
+        }
+    }
+
+    // Store the vertices in the order they're first used, so they're also fetched in order
+    let mut remap = vec![None; data.vertices.len()];
+    let mut vertices = Vec::with_capacity(data.vertices.len());
+    for index in &mut ordered {
+        let vertex = &mut remap[*index as usize];
+        if vertex.is_none() {
+            *vertex = Some(vertices.len() as u32);
+            vertices.push(data.vertices[*index as usize]);
+        }
+        *index = vertex.unwrap();
+    }
+
+    data.vertices = vertices;
+    data.indices = ordered;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use cgmath::{Vector3, InnerSpace};
+    use error::FrontendError;
+    use mesh::MeshData;
+    use meshgen;
+    use meshtools;
+
+    /// Calculates the average amount of vertices transformed per triangle, with a FIFO cache of the
+    /// size the optimization assumes. Lower is better, with 0.5 as the best possible for large grids.
+    fn average_cache_miss_ratio(data: &MeshData) -> f32 {
+        let mut cache = VecDeque::new();
+        let mut misses = 0;
+
+        for &i in &data.indices {
+            if !cache.contains(&i) {
+                misses += 1;
+                cache.push_back(i);
+                if cache.len() > meshtools::CACHE_SIZE {
+                    cache.pop_front();
+                }
+            }
+        }
+
+        misses as f32 / (data.indices.len() / 3) as f32
+    }
+
+    #[test]
+    fn validate_rejects_bad_indices() {
+        let mut data = meshgen::plane(1.0, 1);
+        assert!(meshtools::validate(&data).is_ok());
+
+        data.indices.push(0);
+        match meshtools::validate(&data) {
+            Err(FrontendError::IncompleteTriangle(7)) => (),
+            _ => panic!("incomplete triangle not detected"),
+        }
+
+        data.indices.extend_from_slice(&[1, 4]);
+        match meshtools::validate(&data) {
+            Err(FrontendError::IndexOutOfRange(4, 4)) => (),
+            _ => panic!("index out of range not detected"),
+        }
+    }
+
+    #[test]
+    fn weld_merges_equal_vertices() {
+        // Two triangles sharing an edge, but with separate vertices for it
+        let mut data = meshgen::plane(1.0, 1);
+        let shared: Vec<_> = data.indices.iter().map(|&i| data.vertices[i as usize]).collect();
+        data.vertices = shared;
+        data.indices = (0..6).collect();
+
+        meshtools::weld(&mut data, 1.0e-6);
+        assert_eq!(data.vertices.len(), 4);
+        assert_eq!(data.indices.len(), 6);
+        assert!(meshtools::validate(&data).is_ok());
+    }
+
+    #[test]
+    fn normals_face_outwards() {
+        let mut smooth = meshgen::sphere(1.0, 16, 8);
+        meshtools::smooth_normals(&mut smooth);
+        for vertex in &smooth.vertices {
+            let normal = Vector3::from(vertex.i_normal);
+            assert!(normal.dot(Vector3::from(vertex.i_position)) > 0.95);
+        }
+    }
+
+    #[test]
+    fn prepare_generates_missing_normals() {
+        let mut data = meshgen::sphere(1.0, 16, 8);
+        for vertex in &mut data.vertices {
+            vertex.i_normal = [0.0; 3];
+        }
+
+        meshtools::prepare(&mut data);
+        for vertex in &data.vertices {
+            let normal = Vector3::from(vertex.i_normal);
+            assert!(normal.dot(Vector3::from(vertex.i_position)) > 0.95);
+        }
+
+        // Meshes that come with normals keep them
+        let cube = meshgen::cube(1.0);
+        let mut prepared = cube.clone();
+        meshtools::prepare(&mut prepared);
+        for vertex in &prepared.vertices {
+            assert!(cube.vertices.iter().any(|v| v.i_normal == vertex.i_normal));
+        }
+    }
+
+    #[test]
+    fn tangents_follow_the_texture() {
+        // The plane's U coordinate goes along X, and V along Z
+        let mut data = meshgen::plane(1.0, 2);
+        meshtools::generate_tangents(&mut data);
+        for vertex in &data.vertices {
+            let tangent = Vector3::new(vertex.i_tangent[0], vertex.i_tangent[1], vertex.i_tangent[2]);
+            assert!((tangent - Vector3::unit_x()).magnitude() < 0.001);
+            assert!(vertex.i_tangent[3].abs() == 1.0);
+        }
+    }
+
+    #[test]
+    fn cache_optimization_keeps_triangles() {
+        // Scramble the triangles, like geometry from a file without any useful order
+        let mut data = meshgen::teapot(8);
+        let mut triangles: Vec<_> = data.indices.chunks(3).map(|t| [t[0], t[1], t[2]]).collect();
+        triangles.sort_by_key(|t| t[0].wrapping_mul(2654435761));
+        data.indices = triangles.iter().flat_map(|t| t.iter().cloned()).collect();
+
+        let mut before: Vec<_> = data.indices.chunks(3)
+            .map(|t| [data.vertices[t[0] as usize].i_position, data.vertices[t[1] as usize].i_position,
+                      data.vertices[t[2] as usize].i_position])
+            .collect();
+        let ratio = average_cache_miss_ratio(&data);
+
+        meshtools::optimize_vertex_cache(&mut data);
+        assert!(meshtools::validate(&data).is_ok());
+        assert!(average_cache_miss_ratio(&data) < ratio * 0.5);
+
+        // The same triangles are there with the same winding, just in a different order
+        let mut after: Vec<_> = data.indices.chunks(3)
+            .map(|t| [data.vertices[t[0] as usize].i_position, data.vertices[t[1] as usize].i_position,
+                      data.vertices[t[2] as usize].i_position])
+            .collect();
+        let compare = |a: &[[f32; 3]; 3], b: &[[f32; 3]; 3]| a.partial_cmp(b).unwrap();
+        before.sort_by(&compare);
+        after.sort_by(&compare);
+        assert!(before == after);
+    }
+
+    #[test]
+    fn large_meshes_are_valid() {
+        // More vertices than 16 bit indices can reach
+        let mut data = meshgen::plane(1.0, 300);
+        assert!(data.vertices.len() > 65536);
+        meshtools::prepare(&mut data);
+        assert!(meshtools::validate(&data).is_ok());
+        assert!(data.indices.iter().any(|&i| i > 65535));
+    }
+}
@@ -0,0 +1,242 @@
+use std::sync::Arc;
+
+use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
+use vulkano::command_buffer::{DynamicState, PrimaryCommandBufferBuilder};
+use vulkano::descriptor::descriptor_set::DescriptorPool;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::traits::ImageView;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineParams};
+use vulkano::pipeline::blend::Blend;
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::input_assembly::InputAssembly;
+use vulkano::pipeline::multisample::Multisample;
+use vulkano::pipeline::raster::{Rasterization, CullMode};
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::viewport::{ViewportsState, Viewport, Scissor};
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+
+use error::FrontendError;
+use font::{Font, GlyphSet};
+use {overlay_vs, overlay_fs};
+
+mod renderpass {
+    single_pass_renderpass!{
+        attachments: {
+            color: {
+                load: Load,
+                store: Store,
+                format: ::vulkano::format::Format,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    }
+}
+
+mod pipeline_layout {
+    pipeline_layout!{
+        push_constants: {
+            screen_size: [f32; 2]
+        },
+        set0: {
+            atlas: CombinedImageSampler
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct QuadVertex {
+    i_position: [f32; 2],
+    i_uv: [f32; 2],
+    i_color: [f32; 4],
+}
+
+impl_vertex!(QuadVertex, i_position, i_uv, i_color);
+
+/// Collects screen space quads sampling from a single atlas, so they can be drawn in one call.
+/// Positions are in pixels from the top left of the screen.
+pub struct QuadBatch {
+    white_uv: [f32; 2],
+    vertices: Vec<QuadVertex>,
+}
+
+impl QuadBatch {
+    /// Creates an empty batch, untextured quads sample the atlas at the white texture coordinate.
+    pub fn new(white_uv: [f32; 2]) -> Self {
+        QuadBatch {
+            white_uv: white_uv,
+            vertices: Vec::new(),
+        }
+    }
+
+    pub fn vertices(&self) -> &[QuadVertex] {
+        &self.vertices
+    }
+
+    pub fn quad(&mut self, position: [f32; 2], size: [f32; 2], uv_min: [f32; 2], uv_max: [f32; 2],
+                color: [f32; 4]) {
+        let corner = |x: usize, y: usize| QuadVertex {
+            i_position: [position[0] + size[0] * x as f32, position[1] + size[1] * y as f32],
+            i_uv: [if x == 0 { uv_min[0] } else { uv_max[0] }, if y == 0 { uv_min[1] } else { uv_max[1] }],
+            i_color: color,
+        };
+
+        // Two triangles, there's no index buffer
+        self.vertices.extend_from_slice(&[
+            corner(0, 0), corner(1, 0), corner(1, 1),
+            corner(1, 1), corner(0, 1), corner(0, 0),
+        ]);
+    }
+
+    /// Adds a solid colored rectangle.
+    pub fn rect(&mut self, position: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+        let uv = self.white_uv;
+        self.quad(position, size, uv, uv, color);
+    }
+
+    /// Adds a quad for every visible character, with the top left of the text at the position.
+    pub fn text(&mut self, glyphs: &GlyphSet, text: &str, position: [f32; 2], color: [f32; 4]) {
+        for (i, line) in text.lines().enumerate() {
+            // Glyphs are snapped to whole pixels, so they stay as sharp as they were baked
+            let mut pen = position[0].round();
+            let baseline = (position[1] + glyphs.ascent() + glyphs.line_height() * i as f32).round();
+
+            for c in line.chars() {
+                let glyph = match glyphs.glyph(c) {
+                    Some(glyph) => *glyph,
+                    None => continue,
+                };
+
+                if glyph.size[0] > 0.0 {
+                    self.quad(
+                        [pen + glyph.offset[0], baseline + glyph.offset[1]], glyph.size,
+                        glyph.uv_min, glyph.uv_max, color
+                    );
+                }
+                pen += glyph.advance;
+            }
+        }
+    }
+}
+
+/// Draws screen space quads on top of the final image, after post-processing.
+pub struct OverlayPass {
+    dimensions: [u32; 2],
+    pipeline: Arc<GraphicsPipeline<SingleBufferDefinition<QuadVertex>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>>,
+    set: Arc<pipeline_layout::set0::Set>,
+    framebuffers: Vec<Arc<Framebuffer<renderpass::CustomRenderPass>>>,
+}
+
+impl OverlayPass {
+    /// Creates the pass drawing onto the output images, of the given format and dimensions.
+    pub fn new<I>(device: &Arc<Device>, images: &[Arc<I>], format: Format, dimensions: [u32; 2],
+                  font: &Font) -> Result<Self, FrontendError>
+        where I: ImageView + 'static
+    {
+        let renderpass = try!(renderpass::CustomRenderPass::new(device, &renderpass::Formats {
+            color: (format, 1),
+        }).map_err(FrontendError::RenderPass));
+
+        let vs = try!(overlay_vs::Shader::load(device).map_err(FrontendError::Shader));
+        let fs = try!(overlay_fs::Shader::load(device).map_err(FrontendError::Shader));
+
+        let layout = try!(pipeline_layout::CustomPipeline::new(device)
+            .map_err(FrontendError::PipelineLayout));
+
+        let pipeline = try!(GraphicsPipeline::new(device, GraphicsPipelineParams {
+            vertex_input: SingleBufferDefinition::new(),
+            vertex_shader: vs.main_entry_point(),
+            input_assembly: InputAssembly::triangle_list(),
+            tessellation: None,
+            geometry_shader: None,
+            viewport: ViewportsState::Fixed {
+                data: vec![(
+                    Viewport {
+                        origin: [0.0, 0.0],
+                        depth_range: 0.0 .. 1.0,
+                        dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                    },
+                    Scissor::irrelevant()
+                )],
+            },
+            raster: Rasterization {
+                cull_mode: CullMode::None,
+                .. Default::default()
+            },
+            multisample: Multisample::disabled(),
+            fragment_shader: fs.main_entry_point(),
+            depth_stencil: DepthStencil::disabled(),
+            blend: Blend::alpha_blending(),
+            layout: &layout,
+            render_pass: Subpass::from(&renderpass, 0).unwrap(),
+        }).map_err(FrontendError::Pipeline));
+
+        // Glyphs are drawn at the size they were baked at, so no filtering is needed
+        let sampler = try!(Sampler::new(
+            device, Filter::Nearest, Filter::Nearest, MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge, 0.0, 1.0, 0.0, 0.0
+        ).map_err(FrontendError::Sampler));
+        let set = pipeline_layout::set0::Set::new(
+            &DescriptorPool::new(device), &layout, &pipeline_layout::set0::Descriptors {
+                atlas: (&sampler, font.texture()),
+            }
+        );
+
+        let framebuffers = try!(images.iter().map(|image| {
+            Framebuffer::new(
+                &renderpass, [dimensions[0], dimensions[1], 1],
+                renderpass::AList { color: image }
+            ).map_err(FrontendError::Framebuffer)
+        }).collect::<Result<Vec<_>, _>>());
+
+        Ok(OverlayPass {
+            dimensions: dimensions,
+            pipeline: pipeline,
+            set: set,
+            framebuffers: framebuffers,
+        })
+    }
+
+    /// Records drawing the quads onto an output image, keeping what was already on it.
+    pub fn draw(&self, builder: PrimaryCommandBufferBuilder,
+                vertices: &Arc<CpuAccessibleBuffer<[QuadVertex]>>, image_num: usize)
+                -> PrimaryCommandBufferBuilder {
+        let framebuffer = &self.framebuffers[image_num];
+        let constants = pipeline_layout::PushConstants {
+            screen_size: [self.dimensions[0] as f32, self.dimensions[1] as f32],
+        };
+
+        builder
+            .draw_inline(framebuffer.render_pass(), framebuffer, ())
+            .draw(&self.pipeline, vertices, &DynamicState::none(), &self.set, &constants)
+            .draw_end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use overlay::QuadBatch;
+
+    #[test]
+    fn rect_is_two_triangles() {
+        let mut batch = QuadBatch::new([0.5, 0.5]);
+        batch.rect([10.0, 20.0], [4.0, 2.0], [1.0, 1.0, 1.0, 1.0]);
+
+        let vertices = batch.vertices();
+        assert_eq!(vertices.len(), 6);
+        assert!(vertices.iter().all(|v| v.i_uv == [0.5, 0.5]));
+
+        // The triangles share the diagonal and together cover all four corners
+        let positions: Vec<_> = vertices.iter().map(|v| v.i_position).collect();
+        for corner in &[[10.0, 20.0], [14.0, 20.0], [14.0, 22.0], [10.0, 22.0]] {
+            assert!(positions.contains(corner), "{:?} missing", corner);
+        }
+        assert_eq!(positions[2], positions[3]);
+        assert_eq!(positions[0], positions[5]);
+    }
+}
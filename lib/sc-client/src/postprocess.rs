@@ -0,0 +1,354 @@
+use std::sync::Arc;
+
+use vulkano::buffer::immutable::ImmutableBuffer;
+use vulkano::command_buffer::{DynamicState, PrimaryCommandBufferBuilder};
+use vulkano::descriptor::descriptor_set::DescriptorPool;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::image::traits::ImageView;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineParams};
+use vulkano::pipeline::blend::Blend;
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::input_assembly::InputAssembly;
+use vulkano::pipeline::multisample::Multisample;
+use vulkano::pipeline::raster::{Rasterization, CullMode};
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::viewport::{ViewportsState, Viewport, Scissor};
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+
+use error::FrontendError;
+use mesh;
+use settings::GraphicsSettings;
+use {post_vs, post_bright_fs, post_blur_fs, post_tonemap_fs, post_fxaa_fs};
+
+/// The format the scene is rendered in, allowing colors brighter than the screen can show.
+pub const HDR_FORMAT: Format = Format::R16G16B16A16Sfloat;
+
+/// The format of the tonemapped image, before anti-aliasing.
+const LDR_FORMAT: Format = Format::R8G8B8A8Unorm;
+
+mod renderpass {
+    single_pass_renderpass!{
+        attachments: {
+            color: {
+                load: DontCare,
+                store: Store,
+                format: ::vulkano::format::Format,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    }
+}
+
+mod pipeline_layout {
+    pipeline_layout!{
+        push_constants: {
+            texel_size: [f32; 2],
+            direction: [f32; 2],
+            exposure: f32,
+            bloom_threshold: f32,
+            bloom_intensity: f32,
+            gamma: f32,
+            tonemap: f32
+        },
+        set0: {
+            source: CombinedImageSampler,
+            bloom: CombinedImageSampler
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct PostVertex {
+    i_position: [f32; 2],
+}
+
+impl_vertex!(PostVertex, i_position);
+
+type PostPipeline = GraphicsPipeline<SingleBufferDefinition<PostVertex>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>;
+type Target = Arc<AttachmentImage<Format>>;
+type TargetFramebuffer = Arc<Framebuffer<renderpass::CustomRenderPass>>;
+
+/// Creates a pipeline drawing a fullscreen triangle with the fragment shader.
+macro_rules! post_pipeline {
+    ($device:expr, $vs:expr, $fs:expr, $layout:expr, $renderpass:expr, $dimensions:expr) => {
+        GraphicsPipeline::new($device, GraphicsPipelineParams {
+            vertex_input: SingleBufferDefinition::new(),
+            vertex_shader: $vs.main_entry_point(),
+            input_assembly: InputAssembly::triangle_list(),
+            tessellation: None,
+            geometry_shader: None,
+            viewport: ViewportsState::Fixed {
+                data: vec![(
+                    Viewport {
+                        origin: [0.0, 0.0],
+                        depth_range: 0.0 .. 1.0,
+                        dimensions: [$dimensions[0] as f32, $dimensions[1] as f32],
+                    },
+                    Scissor::irrelevant()
+                )],
+            },
+            raster: Rasterization {
+                cull_mode: CullMode::None,
+                .. Default::default()
+            },
+            multisample: Multisample::disabled(),
+            fragment_shader: $fs.main_entry_point(),
+            depth_stencil: DepthStencil::disabled(),
+            blend: Blend::pass_through(),
+            layout: $layout,
+            render_pass: Subpass::from($renderpass, 0).unwrap(),
+        }).map_err(FrontendError::Pipeline)
+    }
+}
+
+/// Offscreen images and descriptor sets for one frame in flight.
+pub struct PostTargets {
+    hdr: Target,
+    bloom: Option<(Target, TargetFramebuffer, Target, TargetFramebuffer)>,
+    ldr: Option<(Target, TargetFramebuffer)>,
+    bright_set: Arc<pipeline_layout::set0::Set>,
+    blur_sets: (Arc<pipeline_layout::set0::Set>, Arc<pipeline_layout::set0::Set>),
+    tonemap_set: Arc<pipeline_layout::set0::Set>,
+    fxaa_set: Option<Arc<pipeline_layout::set0::Set>>,
+}
+
+impl PostTargets {
+    /// The image the scene should be rendered to.
+    pub fn hdr(&self) -> &Target {
+        &self.hdr
+    }
+}
+
+/// Turns the rendered HDR image into the final image, applying bloom, tonemapping, gamma
+/// correction and anti-aliasing as enabled in the settings.
+pub struct PostChain {
+    device: Arc<Device>,
+    dimensions: [u32; 2],
+    constants: pipeline_layout::PushConstants,
+    bloom: bool,
+    fxaa: bool,
+
+    pool: Arc<DescriptorPool>,
+    layout: Arc<pipeline_layout::CustomPipeline>,
+    sampler: Arc<Sampler>,
+    vertex_buffer: Arc<ImmutableBuffer<[PostVertex]>>,
+
+    hdr_renderpass: Arc<renderpass::CustomRenderPass>,
+    ldr_renderpass: Arc<renderpass::CustomRenderPass>,
+    bright_pipeline: Arc<PostPipeline>,
+    blur_pipeline: Arc<PostPipeline>,
+    tonemap_pipeline: Arc<PostPipeline>,
+    fxaa_pipeline: Arc<PostPipeline>,
+
+    output_framebuffers: Vec<TargetFramebuffer>,
+}
+
+impl PostChain {
+    /// Creates the chain ending in the output images, which are either the swapchain's or
+    /// offscreen ones of the given format and dimensions.
+    pub fn new<I>(device: &Arc<Device>, queue: &Arc<Queue>, settings: &GraphicsSettings,
+                  images: &[Arc<I>], output_format: Format, dimensions: [u32; 2])
+                  -> Result<Self, FrontendError>
+        where I: ImageView + 'static
+    {
+        let bloom_dimensions = [(dimensions[0] / 2).max(1), (dimensions[1] / 2).max(1)];
+
+        // A single triangle covering the whole screen
+        let vertex_buffer = try!(mesh::upload_static(device, queue, &[
+            PostVertex { i_position: [-1.0, -1.0] },
+            PostVertex { i_position: [3.0, -1.0] },
+            PostVertex { i_position: [-1.0, 3.0] },
+        ]));
+
+        let layout = try!(pipeline_layout::CustomPipeline::new(device)
+            .map_err(FrontendError::PipelineLayout));
+        let sampler = try!(Sampler::new(
+            device, Filter::Linear, Filter::Linear, MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge, 0.0, 1.0, 0.0, 0.0
+        ).map_err(FrontendError::Sampler));
+
+        let new_renderpass = |format| renderpass::CustomRenderPass::new(device, &renderpass::Formats {
+            color: (format, 1),
+        }).map_err(FrontendError::RenderPass);
+        let hdr_renderpass = try!(new_renderpass(HDR_FORMAT));
+        let ldr_renderpass = try!(new_renderpass(LDR_FORMAT));
+        let output_renderpass = try!(new_renderpass(output_format));
+
+        let vs = try!(post_vs::Shader::load(device).map_err(FrontendError::Shader));
+        let bright_fs = try!(post_bright_fs::Shader::load(device).map_err(FrontendError::Shader));
+        let blur_fs = try!(post_blur_fs::Shader::load(device).map_err(FrontendError::Shader));
+        let tonemap_fs = try!(post_tonemap_fs::Shader::load(device).map_err(FrontendError::Shader));
+        let fxaa_fs = try!(post_fxaa_fs::Shader::load(device).map_err(FrontendError::Shader));
+
+        // Tonemapping either goes straight to the screen, or to an intermediate image for FXAA
+        let tonemap_renderpass = if settings.fxaa { &ldr_renderpass } else { &output_renderpass };
+        let bright_pipeline = try!(post_pipeline!(
+            device, vs, bright_fs, &layout, &hdr_renderpass, bloom_dimensions
+        ));
+        let blur_pipeline = try!(post_pipeline!(
+            device, vs, blur_fs, &layout, &hdr_renderpass, bloom_dimensions
+        ));
+        let tonemap_pipeline = try!(post_pipeline!(
+            device, vs, tonemap_fs, &layout, tonemap_renderpass, dimensions
+        ));
+        let fxaa_pipeline = try!(post_pipeline!(
+            device, vs, fxaa_fs, &layout, &output_renderpass, dimensions
+        ));
+
+        let output_framebuffers = try!(images.iter().map(|image| {
+            Framebuffer::new(
+                &output_renderpass, [dimensions[0], dimensions[1], 1],
+                renderpass::AList { color: image }
+            ).map_err(FrontendError::Framebuffer)
+        }).collect::<Result<Vec<_>, _>>());
+
+        // Output images in an sRGB format are already gamma corrected by the hardware
+        let gamma = if settings.gamma_correction && !is_srgb(output_format) {
+            settings.gamma
+        } else {
+            1.0
+        };
+
+        Ok(PostChain {
+            device: device.clone(),
+            dimensions: dimensions,
+            constants: pipeline_layout::PushConstants {
+                texel_size: [0.0, 0.0],
+                direction: [0.0, 0.0],
+                exposure: settings.exposure,
+                bloom_threshold: settings.bloom_threshold,
+                bloom_intensity: if settings.bloom { settings.bloom_intensity } else { 0.0 },
+                gamma: gamma,
+                tonemap: if settings.tonemap { 1.0 } else { 0.0 },
+            },
+            bloom: settings.bloom,
+            fxaa: settings.fxaa,
+
+            pool: DescriptorPool::new(device),
+            layout: layout,
+            sampler: sampler,
+            vertex_buffer: vertex_buffer,
+
+            hdr_renderpass: hdr_renderpass,
+            ldr_renderpass: ldr_renderpass,
+            bright_pipeline: bright_pipeline,
+            blur_pipeline: blur_pipeline,
+            tonemap_pipeline: tonemap_pipeline,
+            fxaa_pipeline: fxaa_pipeline,
+
+            output_framebuffers: output_framebuffers,
+        })
+    }
+
+    /// Creates the offscreen images for a frame in flight.
+    pub fn create_targets(&self) -> Result<PostTargets, FrontendError> {
+        let dimensions = self.dimensions;
+        let hdr = try!(AttachmentImage::new(&self.device, dimensions, HDR_FORMAT)
+            .map_err(FrontendError::Image));
+
+        let bloom = if self.bloom {
+            let dimensions = [(dimensions[0] / 2).max(1), (dimensions[1] / 2).max(1)];
+            let (a, a_framebuffer) = try!(self.create_target(&self.hdr_renderpass, dimensions, HDR_FORMAT));
+            let (b, b_framebuffer) = try!(self.create_target(&self.hdr_renderpass, dimensions, HDR_FORMAT));
+            Some((a, a_framebuffer, b, b_framebuffer))
+        } else {
+            None
+        };
+
+        let ldr = if self.fxaa {
+            Some(try!(self.create_target(&self.ldr_renderpass, dimensions, LDR_FORMAT)))
+        } else {
+            None
+        };
+
+        // Without bloom the bloom passes are never run, so their sets just point at the HDR image
+        let (bloom_a, bloom_b) = match bloom {
+            Some((ref a, _, ref b, _)) => (a.clone(), b.clone()),
+            None => (hdr.clone(), hdr.clone()),
+        };
+
+        Ok(PostTargets {
+            bright_set: self.create_set(&hdr, &hdr),
+            blur_sets: (self.create_set(&bloom_a, &bloom_a), self.create_set(&bloom_b, &bloom_b)),
+            tonemap_set: self.create_set(&hdr, &bloom_a),
+            fxaa_set: ldr.as_ref().map(|&(ref image, _)| self.create_set(image, image)),
+            hdr: hdr,
+            bloom: bloom,
+            ldr: ldr,
+        })
+    }
+
+    fn create_target(&self, renderpass: &Arc<renderpass::CustomRenderPass>, dimensions: [u32; 2],
+                     format: Format) -> Result<(Target, TargetFramebuffer), FrontendError> {
+        let image = try!(AttachmentImage::new(&self.device, dimensions, format)
+            .map_err(FrontendError::Image));
+        let framebuffer = try!(Framebuffer::new(
+            renderpass, [dimensions[0], dimensions[1], 1], renderpass::AList { color: &image }
+        ).map_err(FrontendError::Framebuffer));
+
+        Ok((image, framebuffer))
+    }
+
+    fn create_set(&self, source: &Target, bloom: &Target) -> Arc<pipeline_layout::set0::Set> {
+        pipeline_layout::set0::Set::new(&self.pool, &self.layout, &pipeline_layout::set0::Descriptors {
+            source: (&self.sampler, source),
+            bloom: (&self.sampler, bloom),
+        })
+    }
+
+    /// Records all post-processing passes, ending in the swapchain image.
+    pub fn draw(&self, mut builder: PrimaryCommandBufferBuilder, targets: &PostTargets,
+                image_num: usize) -> PrimaryCommandBufferBuilder {
+        let texel = |dimensions: [u32; 2]| [1.0 / dimensions[0] as f32, 1.0 / dimensions[1] as f32];
+        let output = &self.output_framebuffers[image_num];
+
+        // Bloom, extract the bright parts and blur them back and forth between two images
+        if let Some((_, ref a_framebuffer, _, ref b_framebuffer)) = targets.bloom {
+            let bloom_dimensions = [(self.dimensions[0] / 2).max(1), (self.dimensions[1] / 2).max(1)];
+            let mut constants = self.constants;
+
+            constants.texel_size = texel(self.dimensions);
+            builder = self.draw_pass(builder, &self.bright_pipeline, a_framebuffer, &targets.bright_set, &constants);
+
+            constants.texel_size = texel(bloom_dimensions);
+            constants.direction = [1.0, 0.0];
+            builder = self.draw_pass(builder, &self.blur_pipeline, b_framebuffer, &targets.blur_sets.0, &constants);
+            constants.direction = [0.0, 1.0];
+            builder = self.draw_pass(builder, &self.blur_pipeline, a_framebuffer, &targets.blur_sets.1, &constants);
+        }
+
+        let mut constants = self.constants;
+        constants.texel_size = texel(self.dimensions);
+
+        match (targets.ldr.as_ref(), targets.fxaa_set.as_ref()) {
+            (Some(&(_, ref ldr_framebuffer)), Some(fxaa_set)) => {
+                builder = self.draw_pass(builder, &self.tonemap_pipeline, ldr_framebuffer, &targets.tonemap_set, &constants);
+                self.draw_pass(builder, &self.fxaa_pipeline, output, fxaa_set, &constants)
+            },
+            _ => self.draw_pass(builder, &self.tonemap_pipeline, output, &targets.tonemap_set, &constants),
+        }
+    }
+
+    fn draw_pass(&self, builder: PrimaryCommandBufferBuilder, pipeline: &Arc<PostPipeline>,
+                 framebuffer: &TargetFramebuffer, set: &Arc<pipeline_layout::set0::Set>,
+                 constants: &pipeline_layout::PushConstants) -> PrimaryCommandBufferBuilder {
+        builder
+            .draw_inline(framebuffer.render_pass(), framebuffer, ())
+            .draw(pipeline, &self.vertex_buffer, &DynamicState::none(), set, constants)
+            .draw_end()
+    }
+}
+
+fn is_srgb(format: Format) -> bool {
+    match format {
+        Format::R8G8B8A8Srgb | Format::B8G8R8A8Srgb | Format::A8B8G8R8SrgbPack32 => true,
+        _ => false,
+    }
+}
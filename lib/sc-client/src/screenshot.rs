@@ -0,0 +1,175 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use image::{self, ColorType};
+use vulkano::buffer::BufferUsage;
+use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
+use vulkano::device::{Device, Queue};
+use vulkano::format::Format;
+
+use error::FrontendError;
+
+/// Where screenshots are written to, relative to the working directory.
+pub const SCREENSHOT_DIR: &'static str = "screenshots";
+
+/// A frame being copied from its swapchain image to host memory, written to a PNG file once the
+/// frame is done rendering.
+pub struct Capture {
+    buffer: Arc<CpuAccessibleBuffer<[[u8; 4]]>>,
+    dimensions: [u32; 2],
+    format: Format,
+    path: PathBuf,
+}
+
+impl Capture {
+    pub fn new(device: &Arc<Device>, queue: &Arc<Queue>, dimensions: [u32; 2], format: Format,
+               path: PathBuf) -> Result<Self, FrontendError> {
+        if !is_supported(format) {
+            return Err(FrontendError::CaptureFormat(format));
+        }
+
+        let usage = BufferUsage {
+            transfer_dest: true,
+            .. BufferUsage::none()
+        };
+        let len = (dimensions[0] * dimensions[1]) as usize;
+        let buffer = try!(CpuAccessibleBuffer::from_iter(
+            device, &usage, Some(queue.family()), (0 .. len).map(|_| [0u8; 4])
+        ).map_err(FrontendError::Buffer));
+
+        Ok(Capture {
+            buffer: buffer,
+            dimensions: dimensions,
+            format: format,
+            path: path,
+        })
+    }
+
+    pub fn buffer(&self) -> &Arc<CpuAccessibleBuffer<[[u8; 4]]>> {
+        &self.buffer
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the captured frame, the command buffer copying it has to be finished.
+    pub fn save(&self) -> Result<(), FrontendError> {
+        let path_str = self.path.display().to_string();
+        let pixels = try!(self.buffer.read(Duration::new(0, 0))
+            .map_err(FrontendError::BufferAccess));
+        let rgba = to_rgba(&pixels, self.format);
+
+        if let Some(directory) = self.path.parent() {
+            try!(fs::create_dir_all(directory)
+                .map_err(|e| FrontendError::Screenshot(path_str.clone(), e)));
+        }
+        let (width, height) = (self.dimensions[0], self.dimensions[1]);
+        image::save_buffer(&self.path, &rgba, width, height, ColorType::RGBA(8))
+            .map_err(|e| FrontendError::Screenshot(path_str, e))
+    }
+}
+
+/// Checks if frames can be captured from swapchain images that may or may not be used as a copy
+/// source, in the format.
+pub fn check_support(transfer_source: bool, format: Format) -> Result<(), FrontendError> {
+    if !transfer_source {
+        Err(FrontendError::CaptureUsage)
+    } else if !is_supported(format) {
+        Err(FrontendError::CaptureFormat(format))
+    } else {
+        Ok(())
+    }
+}
+
+/// Only 8 bit per channel formats can be captured, these are what the swapchain prefers anyway.
+pub fn is_supported(format: Format) -> bool {
+    match format {
+        Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb |
+        Format::R8G8B8A8Unorm | Format::R8G8B8A8Srgb => true,
+        _ => false,
+    }
+}
+
+/// Converts pixels in one of the supported formats to RGBA. Alpha is set to opaque, since
+/// whatever the frame left in it isn't what was shown on screen.
+pub fn to_rgba(pixels: &[[u8; 4]], format: Format) -> Vec<u8> {
+    let bgra = format == Format::B8G8R8A8Unorm || format == Format::B8G8R8A8Srgb;
+
+    let mut rgba = Vec::with_capacity(pixels.len() * 4);
+    for p in pixels {
+        if bgra {
+            rgba.extend_from_slice(&[p[2], p[1], p[0], 255]);
+        } else {
+            rgba.extend_from_slice(&[p[0], p[1], p[2], 255]);
+        }
+    }
+    rgba
+}
+
+/// Picks a file name for a screenshot taken at the time, numbering screenshots taken within the
+/// same second.
+pub fn screenshot_path(directory: &Path, time: SystemTime) -> PathBuf {
+    let seconds = time.duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+    let name = format!("screenshot-{}", timestamp(seconds));
+
+    let mut path = directory.join(format!("{}.png", name));
+    let mut number = 2;
+    while path.exists() {
+        path = directory.join(format!("{}-{}.png", name, number));
+        number += 1;
+    }
+    path
+}
+
+/// The file name of a frame in a rendered image sequence.
+pub fn sequence_path(directory: &Path, frame: usize) -> PathBuf {
+    directory.join(format!("frame-{:05}.png", frame))
+}
+
+/// Formats seconds since the unix epoch as a UTC date and time that sorts correctly in file
+/// names, like `2017-03-01_14-05-09`.
+pub fn timestamp(seconds: u64) -> String {
+    let (days, time) = (seconds / 86400, seconds % 86400);
+
+    // Converts days to a date in the proleptic Gregorian calendar, with years starting in March
+    // so the leap day is at the end of the year
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}_{:02}-{:02}-{:02}",
+        year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::format::Format;
+    use screenshot;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        assert_eq!(screenshot::timestamp(0), "1970-01-01_00-00-00");
+        assert_eq!(screenshot::timestamp(1488377109), "2017-03-01_14-05-09");
+        assert_eq!(screenshot::timestamp(951868799), "2000-02-29_23-59-59");
+    }
+
+    #[test]
+    fn bgra_is_swizzled_and_opaque() {
+        let pixels = [[10, 20, 30, 0], [1, 2, 3, 128]];
+
+        assert_eq!(screenshot::to_rgba(&pixels, Format::B8G8R8A8Unorm),
+            vec![30, 20, 10, 255, 3, 2, 1, 255]);
+        assert_eq!(screenshot::to_rgba(&pixels, Format::R8G8B8A8Srgb),
+            vec![10, 20, 30, 255, 1, 2, 3, 255]);
+    }
+}
@@ -0,0 +1,313 @@
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::PathBuf;
+
+use cgmath::Vector2;
+use sc_client_game::{ClientGameEvent, ClientWorld};
+use sc_input_data::{Button, TextInput};
+
+use error::FrontendError;
+use frontend::Frontend;
+use hud::HudStats;
+use screenshot;
+
+/// The input the game received during one frame, and the time step it was updated with.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionFrame {
+    pub delta: f32,
+    pub events: Vec<ClientGameEvent>,
+}
+
+/// Writes the game's input to a file frame by frame, so the session can be replayed later.
+///
+/// The file is plain text with one event per line, every frame ends with a `frame` line holding
+/// its time step:
+///
+/// ```text
+/// button move_forward 1
+/// mouse 640 360 1
+/// scroll 0 -1
+/// text char 104
+/// text backspace
+/// focus 0
+/// frame 0.016667
+/// ```
+pub struct SessionRecorder {
+    path: String,
+    file: BufWriter<File>,
+}
+
+impl SessionRecorder {
+    pub fn create(path: &str) -> Result<Self, FrontendError> {
+        let file = try!(File::create(path)
+            .map_err(|e| FrontendError::Session(path.to_string(), e)));
+
+        Ok(SessionRecorder {
+            path: path.to_string(),
+            file: BufWriter::new(file),
+        })
+    }
+
+    pub fn event(&mut self, event: &ClientGameEvent) -> Result<(), FrontendError> {
+        self.write_line(&event_line(event))
+    }
+
+    pub fn end_frame(&mut self, delta: f32) -> Result<(), FrontendError> {
+        self.write_line(&format!("frame {}\n", delta))
+    }
+
+    pub fn finish(mut self) -> Result<(), FrontendError> {
+        let path = &self.path;
+        self.file.flush().map_err(|e| FrontendError::Session(path.clone(), e))
+    }
+
+    fn write_line(&mut self, line: &str) -> Result<(), FrontendError> {
+        let path = &self.path;
+        self.file.write_all(line.as_bytes()).map_err(|e| FrontendError::Session(path.clone(), e))
+    }
+}
+
+/// Reads all frames of a recorded session.
+pub fn load(path: &str) -> Result<Vec<SessionFrame>, FrontendError> {
+    let mut text = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut text))
+        .map_err(|e| FrontendError::Session(path.to_string(), e)));
+
+    parse(&text).map_err(|line| FrontendError::SessionFormat(path.to_string(), line))
+}
+
+/// Plays a recorded session back into an image sequence, one image for every recorded frame.
+pub struct SessionReplay {
+    frames: Vec<SessionFrame>,
+    directory: PathBuf,
+    next_frame: usize,
+}
+
+impl SessionReplay {
+    /// Replays the first `limit` frames of the session, or all of them if it's 0.
+    pub fn new(mut frames: Vec<SessionFrame>, limit: usize, directory: PathBuf) -> Self {
+        if limit != 0 {
+            frames.truncate(limit);
+        }
+
+        SessionReplay {
+            frames: frames,
+            directory: directory,
+            next_frame: 0,
+        }
+    }
+
+    /// Takes the recorded input and time step of the next frame, or None once all were replayed.
+    pub fn next_frame(&mut self) -> Option<SessionFrame> {
+        let frame = self.frames.get(self.next_frame).cloned();
+        if frame.is_some() {
+            self.next_frame += 1;
+        }
+        frame
+    }
+
+    /// Draws the frame last taken with `next_frame`, saving it to the sequence.
+    pub fn render(&self, frontend: &mut Frontend, world: &ClientWorld, stats: &HudStats)
+                  -> Result<(), FrontendError> {
+        let path = screenshot::sequence_path(&self.directory, self.next_frame - 1);
+        try!(frontend.capture_frame(path));
+        frontend.render(world, stats)
+    }
+}
+
+/// Parses the frames in a session file, returning the number of the first invalid line on
+/// failure. Events after the last frame line are part of an unfinished frame and left out.
+pub fn parse(text: &str) -> Result<Vec<SessionFrame>, usize> {
+    let mut frames = Vec::new();
+    let mut events = Vec::new();
+
+    for (i, line) in text.lines().enumerate() {
+        let words: Vec<_> = line.split_whitespace().collect();
+        let invalid = i + 1;
+
+        match (words.get(0).cloned(), words.len()) {
+            (None, _) => (),
+            (Some("frame"), 2) => frames.push(SessionFrame {
+                delta: try!(words[1].parse().map_err(|_| invalid)),
+                events: events.split_off(0),
+            }),
+            (Some("button"), 3) => match (parse_button(words[1]), parse_flag(words[2])) {
+                (Some(button), Some(down)) =>
+                    events.push(ClientGameEvent::ButtonState(button, down)),
+                _ => return Err(invalid),
+            },
+            (Some("mouse"), 4) => {
+                let (x, y, track) = (words[1].parse(), words[2].parse(), parse_flag(words[3]));
+                match (x, y, track) {
+                    (Ok(x), Ok(y), Some(track)) =>
+                        events.push(ClientGameEvent::MouseMove(Vector2::new(x, y), track)),
+                    _ => return Err(invalid),
+                }
+            },
+            (Some("scroll"), 3) => match (words[1].parse(), words[2].parse()) {
+                (Ok(x), Ok(y)) => events.push(ClientGameEvent::Scroll(Vector2::new(x, y))),
+                _ => return Err(invalid),
+            },
+            (Some("text"), 2) => match parse_text_key(words[1]) {
+                Some(text) => events.push(ClientGameEvent::Text(text)),
+                None => return Err(invalid),
+            },
+            (Some("text"), 3) if words[1] == "char" => {
+                // Characters are stored as code points, so spaces don't split the line
+                match words[2].parse().ok().and_then(::std::char::from_u32) {
+                    Some(c) => events.push(ClientGameEvent::Text(TextInput::Char(c))),
+                    None => return Err(invalid),
+                }
+            },
+            (Some("focus"), 2) => match parse_flag(words[1]) {
+                Some(focused) => events.push(ClientGameEvent::Focus(focused)),
+                None => return Err(invalid),
+            },
+            _ => return Err(invalid),
+        }
+    }
+
+    Ok(frames)
+}
+
+fn event_line(event: &ClientGameEvent) -> String {
+    match *event {
+        ClientGameEvent::ButtonState(button, down) =>
+            format!("button {} {}\n", button_name(button), down as u8),
+        ClientGameEvent::MouseMove(position, track) =>
+            format!("mouse {} {} {}\n", position.x, position.y, track as u8),
+        ClientGameEvent::Text(TextInput::Char(c)) => format!("text char {}\n", c as u32),
+        ClientGameEvent::Text(text) => format!("text {}\n", text_key_name(text)),
+        ClientGameEvent::Scroll(lines) => format!("scroll {} {}\n", lines.x, lines.y),
+        ClientGameEvent::Focus(focused) => format!("focus {}\n", focused as u8),
+    }
+}
+
+fn parse_flag(word: &str) -> Option<bool> {
+    match word {
+        "0" => Some(false),
+        "1" => Some(true),
+        _ => None,
+    }
+}
+
+fn button_name(button: Button) -> &'static str {
+    match button {
+        Button::MoveForward => "move_forward",
+        Button::MoveBackward => "move_backward",
+        Button::MoveRight => "move_right",
+        Button::MoveLeft => "move_left",
+        Button::Menu => "menu",
+        Button::Fire => "fire",
+        Button::Aim => "aim",
+        Button::Console => "console",
+        Button::Chat => "chat",
+        Button::Confirm => "confirm",
+        Button::__DoNotMatch => "unknown",
+    }
+}
+
+fn parse_button(name: &str) -> Option<Button> {
+    match name {
+        "move_forward" => Some(Button::MoveForward),
+        "move_backward" => Some(Button::MoveBackward),
+        "move_right" => Some(Button::MoveRight),
+        "move_left" => Some(Button::MoveLeft),
+        "menu" => Some(Button::Menu),
+        "fire" => Some(Button::Fire),
+        "aim" => Some(Button::Aim),
+        "console" => Some(Button::Console),
+        "chat" => Some(Button::Chat),
+        "confirm" => Some(Button::Confirm),
+        _ => None,
+    }
+}
+
+fn text_key_name(text: TextInput) -> &'static str {
+    match text {
+        TextInput::Char(_) => "char",
+        TextInput::Backspace => "backspace",
+        TextInput::Delete => "delete",
+        TextInput::Enter => "enter",
+        TextInput::Left => "left",
+        TextInput::Right => "right",
+        TextInput::Up => "up",
+        TextInput::Down => "down",
+        TextInput::Home => "home",
+        TextInput::End => "end",
+    }
+}
+
+fn parse_text_key(name: &str) -> Option<TextInput> {
+    match name {
+        "backspace" => Some(TextInput::Backspace),
+        "delete" => Some(TextInput::Delete),
+        "enter" => Some(TextInput::Enter),
+        "left" => Some(TextInput::Left),
+        "right" => Some(TextInput::Right),
+        "up" => Some(TextInput::Up),
+        "down" => Some(TextInput::Down),
+        "home" => Some(TextInput::Home),
+        "end" => Some(TextInput::End),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use cgmath::Vector2;
+    use sc_client_game::{ClientGameEvent, ClientWorld};
+    use sc_input_data::{Button, TextInput};
+    use session::{self, SessionFrame, SessionReplay};
+
+    #[test]
+    fn frames_round_trip() {
+        let frames = vec![
+            SessionFrame {
+                delta: 0.016,
+                events: vec![
+                    ClientGameEvent::ButtonState(Button::MoveLeft, true),
+                    ClientGameEvent::MouseMove(Vector2::new(-3, 700), false),
+                    ClientGameEvent::Scroll(Vector2::new(0.0, -1.5)),
+                    ClientGameEvent::Text(TextInput::Char(' ')),
+                    ClientGameEvent::Text(TextInput::Enter),
+                    ClientGameEvent::Focus(false),
+                ],
+            },
+            SessionFrame { delta: 0.5, events: Vec::new() },
+        ];
+
+        let mut text = String::new();
+        for frame in &frames {
+            for event in &frame.events {
+                text.push_str(&session::event_line(event));
+            }
+            text.push_str(&format!("frame {}\n", frame.delta));
+        }
+
+        assert_eq!(session::parse(&text), Ok(frames));
+    }
+
+    #[test]
+    fn reports_invalid_lines() {
+        assert_eq!(session::parse("frame 0.1\n\nbutton jump 1\n"), Err(3));
+        assert_eq!(session::parse("mouse 1 2\n"), Err(1));
+
+        // The unfinished last frame is dropped
+        assert_eq!(session::parse("frame 0.1\nbutton menu 1\n").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn replay_stops_at_the_limit() {
+        let frames = vec![SessionFrame { delta: 0.1, events: Vec::new() }; 3];
+
+        let mut replay = SessionReplay::new(frames.clone(), 2, PathBuf::from("frames"));
+        assert!(replay.next_frame().is_some());
+        assert!(replay.next_frame().is_some());
+        assert_eq!(replay.next_frame(), None);
+
+        let mut whole = SessionReplay::new(frames, 0, PathBuf::from("frames"));
+        assert_eq!((0 .. 4).filter_map(|_| whole.next_frame()).count(), 3);
+    }
+}
@@ -0,0 +1,294 @@
+use cgmath::Vector2;
+use vulkano::format::Format;
+use vulkano::instance::PhysicalDevice;
+use vulkano::swapchain::{Capabilities, PresentMode};
+
+use config::{Config, ConfigError};
+use shadow::MAX_CASCADES;
+
+/// Graphics settings, read from the `[graphics]` section of the client config.
+#[derive(Clone, Debug)]
+pub struct GraphicsSettings {
+    pub resolution: Vector2<u32>,
+    pub fullscreen: bool,
+    pub present_mode: PresentMode,
+    pub fov: f32, // vertical, in degrees
+    pub near: f32,
+    pub far: f32,
+    pub msaa: u32, // samples per pixel, anything but 1 is rejected while validating
+    pub depth_format: Format,
+    pub shadows: bool,
+    pub shadow_resolution: u32, // per cascade
+    pub shadow_cascades: u32,
+    pub shadow_distance: f32, // how far from the camera shadows are drawn
+    pub shadow_pcf: u32, // filter radius in texels, 0 for hard shadows
+    pub tonemap: bool,
+    pub exposure: f32,
+    pub gamma_correction: bool,
+    pub gamma: f32,
+    pub bloom: bool,
+    pub bloom_threshold: f32,
+    pub bloom_intensity: f32,
+    pub fxaa: bool,
+    pub hud: bool,
+    pub hud_font_size: f32, // in pixels
+    pub mesh_detail: u32, // subdivisions of curved generated meshes
+}
+
+impl GraphicsSettings {
+    pub fn from_config(config: &Config) -> Result<Self, ConfigError> {
+        let default = GraphicsSettings::default();
+
+        let present_mode = match try!(config.get::<String>("graphics.present_mode")) {
+            Some(value) => try!(parse_present_mode(&value)
+                .ok_or(ConfigError::InvalidValue("graphics.present_mode".into(), value))),
+            None => default.present_mode,
+        };
+        let depth_format = match try!(config.get::<String>("graphics.depth_format")) {
+            Some(value) => try!(parse_depth_format(&value)
+                .ok_or(ConfigError::InvalidValue("graphics.depth_format".into(), value))),
+            None => default.depth_format,
+        };
+
+        Ok(GraphicsSettings {
+            resolution: Vector2::new(
+                try!(config.get_or("graphics.width", default.resolution.x)),
+                try!(config.get_or("graphics.height", default.resolution.y)),
+            ),
+            fullscreen: try!(config.get_or("graphics.fullscreen", default.fullscreen)),
+            present_mode: present_mode,
+            fov: try!(config.get_or("graphics.fov", default.fov)),
+            near: try!(config.get_or("graphics.near", default.near)),
+            far: try!(config.get_or("graphics.far", default.far)),
+            msaa: try!(config.get_or("graphics.msaa", default.msaa)),
+            depth_format: depth_format,
+            shadows: try!(config.get_or("graphics.shadows", default.shadows)),
+            shadow_resolution: try!(config.get_or("graphics.shadow_resolution", default.shadow_resolution)),
+            shadow_cascades: try!(config.get_or("graphics.shadow_cascades", default.shadow_cascades)),
+            shadow_distance: try!(config.get_or("graphics.shadow_distance", default.shadow_distance)),
+            shadow_pcf: try!(config.get_or("graphics.shadow_pcf", default.shadow_pcf)),
+            tonemap: try!(config.get_or("graphics.tonemap", default.tonemap)),
+            exposure: try!(config.get_or("graphics.exposure", default.exposure)),
+            gamma_correction: try!(config.get_or("graphics.gamma_correction", default.gamma_correction)),
+            gamma: try!(config.get_or("graphics.gamma", default.gamma)),
+            bloom: try!(config.get_or("graphics.bloom", default.bloom)),
+            bloom_threshold: try!(config.get_or("graphics.bloom_threshold", default.bloom_threshold)),
+            bloom_intensity: try!(config.get_or("graphics.bloom_intensity", default.bloom_intensity)),
+            fxaa: try!(config.get_or("graphics.fxaa", default.fxaa)),
+            hud: try!(config.get_or("graphics.hud", default.hud)),
+            hud_font_size: try!(config.get_or("graphics.hud_font_size", default.hud_font_size)),
+            mesh_detail: try!(config.get_or("graphics.mesh_detail", default.mesh_detail)),
+        })
+    }
+
+    /// Shrinks the resolution to fit the monitor. This is done before the window is created, the
+    /// surface's own limits are only known once it exists.
+    pub fn fit_monitor(&mut self, monitor: (u32, u32)) {
+        // Some platforms don't know the monitor's size
+        if monitor.0 == 0 || monitor.1 == 0 {
+            return;
+        }
+
+        let fitted = Vector2::new(
+            clamp(self.resolution.x, 1, monitor.0),
+            clamp(self.resolution.y, 1, monitor.1),
+        );
+        if fitted != self.resolution {
+            println!("Resolution {:?} doesn't fit the monitor, using {:?}", self.resolution, fitted);
+            self.resolution = fitted;
+        }
+    }
+
+    /// Adjusts the settings to what the device and surface actually support, logging every change
+    /// made. Without a surface, when rendering offscreen, only the device's limits apply.
+    pub fn validate(&mut self, physical: &PhysicalDevice, caps: Option<&Capabilities>) {
+        // The resolution has to be within the surface's limits, or the largest image possible
+        let (min, max) = match caps {
+            Some(caps) => (caps.min_image_extent, caps.max_image_extent),
+            None => {
+                let max = physical.limits().max_image_dimension_2d();
+                ([1, 1], [max, max])
+            },
+        };
+        let clamped = Vector2::new(
+            clamp(self.resolution.x, min[0], max[0]),
+            clamp(self.resolution.y, min[1], max[1]),
+        );
+        if clamped != self.resolution {
+            println!("Resolution {:?} not supported, using {:?}", self.resolution, clamped);
+            self.resolution = clamped;
+        }
+
+        // Fifo is guaranteed to be supported, so fall back to that
+        if let Some(caps) = caps {
+            if !caps.present_modes.supports(self.present_mode) {
+                println!("Present mode {:?} not supported, using Fifo", self.present_mode);
+                self.present_mode = PresentMode::Fifo;
+            }
+        }
+
+        if self.near <= 0.0 || self.far <= self.near {
+            println!("Clip planes {}..{} are invalid, using defaults", self.near, self.far);
+            let default = GraphicsSettings::default();
+            self.near = default.near;
+            self.far = default.far;
+        }
+
+        // vulkano 0.2's render passes have no resolve attachments, so a multisampled scene could
+        // never be turned into the single sampled image post-processing reads
+        if self.msaa != 1 {
+            println!("MSAA x{} is not supported, rendering without it, use FXAA instead",
+                self.msaa);
+            self.msaa = 1;
+        }
+
+        if self.fov <= 0.0 || self.fov >= 180.0 {
+            println!("FOV {} is out of range, using default", self.fov);
+            self.fov = GraphicsSettings::default().fov;
+        }
+
+        let cascades = clamp(self.shadow_cascades, 1, MAX_CASCADES as u32);
+        if cascades != self.shadow_cascades {
+            println!("{} shadow cascades not supported, using {}", self.shadow_cascades, cascades);
+            self.shadow_cascades = cascades;
+        }
+
+        // All cascades are placed side by side in a single shadow map
+        let limits = physical.limits();
+        let max_resolution = limits.max_image_dimension_2d() / self.shadow_cascades;
+        let resolution = clamp(self.shadow_resolution, 16, max_resolution);
+        if resolution != self.shadow_resolution {
+            println!("Shadow resolution {} not supported, using {}", self.shadow_resolution, resolution);
+            self.shadow_resolution = resolution;
+        }
+
+        if self.shadow_distance <= self.near {
+            println!("Shadow distance {} is invalid, using default", self.shadow_distance);
+            self.shadow_distance = GraphicsSettings::default().shadow_distance;
+        }
+
+        if self.gamma <= 0.0 || self.exposure <= 0.0 {
+            println!("Gamma {} or exposure {} is invalid, using defaults", self.gamma, self.exposure);
+            let default = GraphicsSettings::default();
+            self.gamma = default.gamma;
+            self.exposure = default.exposure;
+        }
+
+        if self.hud_font_size < 6.0 || self.hud_font_size > 72.0 {
+            println!("HUD font size {} is out of range, using default", self.hud_font_size);
+            self.hud_font_size = GraphicsSettings::default().hud_font_size;
+        }
+
+        // Generating gets slow past this, the teapot alone has over 130000 vertices at 64
+        let detail = clamp(self.mesh_detail, 1, 64);
+        if detail != self.mesh_detail {
+            println!("Mesh detail {} is out of range, using {}", self.mesh_detail, detail);
+            self.mesh_detail = detail;
+        }
+    }
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        GraphicsSettings {
+            resolution: Vector2::new(1280, 720),
+            fullscreen: false,
+            present_mode: PresentMode::Fifo,
+            fov: 90.0,
+            near: 0.01,
+            far: 100.0,
+            msaa: 1,
+            depth_format: Format::D16Unorm,
+            shadows: true,
+            shadow_resolution: 2048,
+            shadow_cascades: 3,
+            shadow_distance: 50.0,
+            shadow_pcf: 1,
+            tonemap: true,
+            exposure: 1.0,
+            gamma_correction: true,
+            gamma: 2.2,
+            bloom: true,
+            bloom_threshold: 1.0,
+            bloom_intensity: 0.5,
+            fxaa: false,
+            hud: true,
+            hud_font_size: 16.0,
+            mesh_detail: 8,
+        }
+    }
+}
+
+fn parse_present_mode(value: &str) -> Option<PresentMode> {
+    match value {
+        "immediate" => Some(PresentMode::Immediate),
+        "mailbox" => Some(PresentMode::Mailbox),
+        "fifo" | "vsync" => Some(PresentMode::Fifo),
+        "relaxed" => Some(PresentMode::Relaxed),
+        _ => None,
+    }
+}
+
+fn parse_depth_format(value: &str) -> Option<Format> {
+    match value {
+        "d16" => Some(Format::D16Unorm),
+        "d24" => Some(Format::X8_D24UnormPack32),
+        "d32" => Some(Format::D32Sfloat),
+        _ => None,
+    }
+}
+
+fn clamp(value: u32, min: u32, max: u32) -> u32 {
+    if value < min { min } else if value > max { max } else { value }
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::format::Format;
+    use vulkano::swapchain::PresentMode;
+    use config::Config;
+    use settings::GraphicsSettings;
+
+    #[test]
+    fn missing_keys_use_defaults() {
+        let settings = GraphicsSettings::from_config(&Config::new()).unwrap();
+
+        assert_eq!(settings.resolution, GraphicsSettings::default().resolution);
+        assert_eq!(settings.present_mode, PresentMode::Fifo);
+    }
+
+    #[test]
+    fn reads_graphics_section() {
+        let config = Config::parse(
+            "[graphics]\nwidth = 1920\nheight = 1080\npresent_mode = mailbox\ndepth_format = d32\nfov = 75\n\
+             msaa = 4\nshadows = false\nshadow_cascades = 2"
+        ).unwrap();
+        let settings = GraphicsSettings::from_config(&config).unwrap();
+
+        assert_eq!(settings.resolution.x, 1920);
+        assert_eq!(settings.resolution.y, 1080);
+        assert_eq!(settings.present_mode, PresentMode::Mailbox);
+        assert_eq!(settings.depth_format, Format::D32Sfloat);
+        assert_eq!(settings.fov, 75.0);
+        assert_eq!(settings.msaa, 4);
+        assert_eq!(settings.shadows, false);
+        assert_eq!(settings.shadow_cascades, 2);
+    }
+
+    #[test]
+    fn resolution_fits_monitor() {
+        let mut settings = GraphicsSettings::default();
+
+        settings.fit_monitor((1024, 768));
+        assert_eq!((settings.resolution.x, settings.resolution.y), (1024, 720));
+
+        settings.fit_monitor((0, 0));
+        assert_eq!((settings.resolution.x, settings.resolution.y), (1024, 720));
+    }
+
+    #[test]
+    fn rejects_unknown_present_mode() {
+        let config = Config::parse("[graphics]\npresent_mode = sometimes").unwrap();
+        assert!(GraphicsSettings::from_config(&config).is_err());
+    }
+}
@@ -0,0 +1,18 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec3 i_position;
+layout(location = 1) in vec3 i_color;
+
+layout(location = 0) out vec3 o_color;
+
+layout(push_constant) uniform PushConstants {
+    mat4 view_proj;
+} push;
+
+void main() {
+    o_color = i_color;
+    gl_Position = push.view_proj * vec4(i_position, 1.0);
+}
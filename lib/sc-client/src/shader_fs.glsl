@@ -3,16 +3,118 @@
 #extension GL_ARB_separate_shader_objects : enable
 #extension GL_ARB_shading_language_420pack : enable
 
+#define MAX_POINT_LIGHTS 8
+#define MAX_CASCADES 4
+
 layout(location = 0) in vec3 i_normal;
+layout(location = 1) in vec3 i_position;
+layout(location = 2) in vec2 i_uv;
 
 layout(location = 0) out vec4 o_color;
 
-const vec3 LIGHT = vec3(0.0, 0.0, 1.0);
+// All positions and directions are in view space
+layout(set = 0, binding = 1) uniform Lighting {
+    vec4 sun_direction; // towards the sun
+    vec4 sun_color; // premultiplied with intensity
+    vec4 ambient_color;
+    vec4 point_positions[MAX_POINT_LIGHTS]; // w is range
+    vec4 point_colors[MAX_POINT_LIGHTS]; // premultiplied with intensity
+    int point_count;
+    vec4 fog_color; // w is density
+} lighting;
+
+layout(set = 0, binding = 2) uniform Shadow {
+    mat4 cascade_matrices[MAX_CASCADES]; // view space to shadow map coordinates and depth
+    vec4 cascade_splits; // view distance at which every cascade ends
+    vec2 texel_size;
+    int cascade_count; // 0 if shadows are disabled
+    int pcf_radius;
+} shadow;
+
+layout(set = 0, binding = 3) uniform sampler2D shadow_map;
+
+layout(set = 1, binding = 0) uniform Material {
+    vec4 base_color;
+    float roughness;
+    float specular;
+} material;
+
+layout(set = 1, binding = 1) uniform sampler2D albedo;
+
+// Blinn-Phong diffuse and specular contribution of a single light
+vec3 shade(vec3 base, float shininess, vec3 normal, vec3 to_eye, vec3 to_light, vec3 color) {
+    float diffuse = max(dot(normal, to_light), 0.0);
+    if (diffuse <= 0.0) {
+        return vec3(0.0);
+    }
+
+    vec3 halfway = normalize(to_light + to_eye);
+    float specular = pow(max(dot(normal, halfway), 0.0), shininess) * material.specular;
+
+    return (base * diffuse + vec3(specular)) * color;
+}
+
+// How much of the sun reaches the position, between 0.0 and 1.0
+float sun_visibility(vec3 position) {
+    float distance = -position.z;
+    if (shadow.cascade_count == 0 || distance > shadow.cascade_splits[shadow.cascade_count - 1]) {
+        return 1.0;
+    }
+
+    int cascade = 0;
+    while (distance > shadow.cascade_splits[cascade]) {
+        cascade++;
+    }
+
+    // Keep the filter from sampling the neighbouring cascades in the shadow map
+    vec3 coords = (shadow.cascade_matrices[cascade] * vec4(position, 1.0)).xyz;
+    float tile = 1.0 / float(shadow.cascade_count);
+    vec2 min_uv = vec2(tile * float(cascade), 0.0) + shadow.texel_size * 0.5;
+    vec2 max_uv = vec2(tile * float(cascade + 1), 1.0) - shadow.texel_size * 0.5;
+
+    // Percentage closer filtering, averaging the depth test over the surrounding texels
+    float lit = 0.0;
+    for (int x = -shadow.pcf_radius; x <= shadow.pcf_radius; x++) {
+        for (int y = -shadow.pcf_radius; y <= shadow.pcf_radius; y++) {
+            vec2 uv = clamp(coords.xy + vec2(x, y) * shadow.texel_size, min_uv, max_uv);
+            lit += coords.z <= texture(shadow_map, uv).r ? 1.0 : 0.0;
+        }
+    }
+
+    float size = float(shadow.pcf_radius * 2 + 1);
+    return lit / (size * size);
+}
 
 void main() {
-    float brightness = dot(normalize(i_normal), normalize(LIGHT));
-    vec3 dark_color = vec3(0.6, 0.0, 0.0);
-    vec3 regular_color = vec3(1.0, 0.0, 0.0);
+    vec3 normal = normalize(i_normal);
+    vec3 to_eye = normalize(-i_position);
+    vec3 base = texture(albedo, i_uv).rgb * material.base_color.rgb;
+
+    // Map roughness to a Blinn-Phong exponent, rough surfaces get wide and dim highlights
+    float roughness = clamp(material.roughness, 0.1, 1.0);
+    float shininess = 2.0 / (roughness * roughness * roughness * roughness) - 2.0;
+
+    vec3 color = base * lighting.ambient_color.rgb;
+    color += shade(base, shininess, normal, to_eye,
+                   normalize(lighting.sun_direction.xyz), lighting.sun_color.rgb)
+        * sun_visibility(i_position);
+
+    for (int i = 0; i < lighting.point_count; i++) {
+        vec3 offset = lighting.point_positions[i].xyz - i_position;
+        float distance = length(offset);
+        float range = lighting.point_positions[i].w;
+
+        // Smooth falloff that reaches zero at the light's range
+        float falloff = clamp(1.0 - pow(distance / range, 2.0), 0.0, 1.0);
+        falloff *= falloff;
+
+        color += shade(base, shininess, normal, to_eye,
+                       offset / distance, lighting.point_colors[i].rgb) * falloff;
+    }
+
+    // Exponential fog, fading into the sky's color at the horizon
+    float fog = exp(-lighting.fog_color.w * length(i_position));
+    color = mix(lighting.fog_color.rgb, color, fog);
 
-    o_color = vec4(mix(dark_color, regular_color, brightness), 1.0);
+    o_color = vec4(color, 1.0);
 }
@@ -0,0 +1,15 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_uv;
+layout(location = 1) in vec4 i_color;
+
+layout(location = 0) out vec4 o_color;
+
+layout(set = 0, binding = 0) uniform sampler2D atlas;
+
+void main() {
+    o_color = texture(atlas, i_uv) * i_color;
+}
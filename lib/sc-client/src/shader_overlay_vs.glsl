@@ -0,0 +1,22 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_position;
+layout(location = 1) in vec2 i_uv;
+layout(location = 2) in vec4 i_color;
+
+layout(location = 0) out vec2 o_uv;
+layout(location = 1) out vec4 o_color;
+
+layout(push_constant) uniform PushConstants {
+    vec2 screen_size;
+} push;
+
+// Positions are in pixels from the top left of the screen
+void main() {
+    o_uv = i_uv;
+    o_color = i_color;
+    gl_Position = vec4(i_position / push.screen_size * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,35 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_uv;
+
+layout(location = 0) out vec4 o_color;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texel_size; // of the source
+    vec2 direction; // of the blur
+    float exposure;
+    float bloom_threshold;
+    float bloom_intensity;
+    float gamma; // 1.0 disables gamma correction
+    float tonemap; // 1.0 enables tonemapping, 0.0 clamps instead
+} push;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+const float WEIGHTS[5] = float[](0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+// One direction of a separable gaussian blur
+void main() {
+    vec2 step = push.direction * push.texel_size;
+    vec3 color = texture(source, i_uv).rgb * WEIGHTS[0];
+
+    for (int i = 1; i < 5; i++) {
+        color += texture(source, i_uv + step * float(i)).rgb * WEIGHTS[i];
+        color += texture(source, i_uv - step * float(i)).rgb * WEIGHTS[i];
+    }
+
+    o_color = vec4(color, 1.0);
+}
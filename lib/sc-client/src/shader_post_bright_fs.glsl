@@ -0,0 +1,29 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_uv;
+
+layout(location = 0) out vec4 o_color;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texel_size; // of the source
+    vec2 direction; // of the blur
+    float exposure;
+    float bloom_threshold;
+    float bloom_intensity;
+    float gamma; // 1.0 disables gamma correction
+    float tonemap; // 1.0 enables tonemapping, 0.0 clamps instead
+} push;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+// Keeps only the parts of the image brighter than the threshold, at half resolution
+void main() {
+    vec3 color = texture(source, i_uv).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+    float contribution = max(brightness - push.bloom_threshold, 0.0) / max(brightness, 0.0001);
+
+    o_color = vec4(color * contribution, 1.0);
+}
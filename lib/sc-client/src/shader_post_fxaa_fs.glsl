@@ -0,0 +1,62 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_uv;
+
+layout(location = 0) out vec4 o_color;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texel_size; // of the source
+    vec2 direction; // of the blur
+    float exposure;
+    float bloom_threshold;
+    float bloom_intensity;
+    float gamma; // 1.0 disables gamma correction
+    float tonemap; // 1.0 enables tonemapping, 0.0 clamps instead
+} push;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+float luma(vec3 color) {
+    return dot(color, vec3(0.299, 0.587, 0.114));
+}
+
+// Fast approximate anti-aliasing, blurs along edges found through the luma of the neighbours
+void main() {
+    vec2 texel = push.texel_size;
+    float luma_nw = luma(texture(source, i_uv + vec2(-1.0, -1.0) * texel).rgb);
+    float luma_ne = luma(texture(source, i_uv + vec2(1.0, -1.0) * texel).rgb);
+    float luma_sw = luma(texture(source, i_uv + vec2(-1.0, 1.0) * texel).rgb);
+    float luma_se = luma(texture(source, i_uv + vec2(1.0, 1.0) * texel).rgb);
+    float luma_m = luma(texture(source, i_uv).rgb);
+
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    vec2 direction = vec2(
+        -((luma_nw + luma_ne) - (luma_sw + luma_se)),
+        (luma_nw + luma_sw) - (luma_ne + luma_se)
+    );
+    float reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float scale = 1.0 / (min(abs(direction.x), abs(direction.y)) + reduce);
+    direction = clamp(direction * scale, -SPAN_MAX, SPAN_MAX) * texel;
+
+    vec3 near = 0.5 * (
+        texture(source, i_uv + direction * (1.0 / 3.0 - 0.5)).rgb +
+        texture(source, i_uv + direction * (2.0 / 3.0 - 0.5)).rgb
+    );
+    vec3 far = near * 0.5 + 0.25 * (
+        texture(source, i_uv - direction * 0.5).rgb +
+        texture(source, i_uv + direction * 0.5).rgb
+    );
+
+    // If the wider sample picked up a different edge, fall back to the narrow one
+    float luma_far = luma(far);
+    o_color = vec4((luma_far < luma_min || luma_far > luma_max) ? near : far, 1.0);
+}
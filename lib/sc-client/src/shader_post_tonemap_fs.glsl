@@ -0,0 +1,36 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_uv;
+
+layout(location = 0) out vec4 o_color;
+
+layout(push_constant) uniform PushConstants {
+    vec2 texel_size; // of the source
+    vec2 direction; // of the blur
+    float exposure;
+    float bloom_threshold;
+    float bloom_intensity;
+    float gamma; // 1.0 disables gamma correction
+    float tonemap; // 1.0 enables tonemapping, 0.0 clamps instead
+} push;
+
+layout(set = 0, binding = 0) uniform sampler2D source;
+layout(set = 0, binding = 1) uniform sampler2D bloom;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 aces(vec3 color) {
+    return clamp((color * (2.51 * color + 0.03)) / (color * (2.43 * color + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec3 color = texture(source, i_uv).rgb + texture(bloom, i_uv).rgb * push.bloom_intensity;
+    color *= push.exposure;
+
+    color = mix(clamp(color, 0.0, 1.0), aces(color), push.tonemap);
+    color = pow(color, vec3(1.0 / push.gamma));
+
+    o_color = vec4(color, 1.0);
+}
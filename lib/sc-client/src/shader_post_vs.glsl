@@ -0,0 +1,13 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_position;
+
+layout(location = 0) out vec2 o_uv;
+
+void main() {
+    o_uv = i_position * 0.5 + 0.5;
+    gl_Position = vec4(i_position, 0.0, 1.0);
+}
@@ -0,0 +1,8 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+// Only depth is written during the shadow pass
+void main() {
+}
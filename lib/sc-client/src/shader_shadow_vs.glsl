@@ -0,0 +1,15 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec3 i_position;
+layout(location = 1) in mat4 i_model;
+
+layout(push_constant) uniform PushConstants {
+    mat4 light_view_proj;
+} push;
+
+void main() {
+    gl_Position = push.light_view_proj * i_model * vec4(i_position, 1.0);
+}
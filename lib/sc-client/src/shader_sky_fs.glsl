@@ -0,0 +1,31 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec3 i_direction;
+
+layout(location = 0) out vec4 o_color;
+
+layout(push_constant) uniform PushConstants {
+    mat4 inverse_view_proj; // without the camera's translation
+    vec4 zenith_color;
+    vec4 horizon_color;
+    vec4 sun_direction; // towards the sun
+    vec4 sun_color; // w is 1.0 if the sun's disc should be drawn
+} push;
+
+void main() {
+    vec3 direction = normalize(i_direction);
+
+    // Below the horizon the sky matches the fog, so distant geometry blends into it
+    float elevation = max(direction.y, 0.0);
+    vec3 color = mix(push.horizon_color.rgb, push.zenith_color.rgb, sqrt(elevation));
+
+    // A sharp disc with a soft glow around it
+    float sun = max(dot(direction, normalize(push.sun_direction.xyz)), 0.0);
+    float disc = smoothstep(0.9995, 0.9998, sun) + pow(sun, 256.0) * 0.5;
+    color += push.sun_color.rgb * disc * push.sun_color.w;
+
+    o_color = vec4(color, 1.0);
+}
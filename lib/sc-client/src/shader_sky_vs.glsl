@@ -0,0 +1,24 @@
+#version 450
+
+#extension GL_ARB_separate_shader_objects : enable
+#extension GL_ARB_shading_language_420pack : enable
+
+layout(location = 0) in vec2 i_position;
+
+layout(location = 0) out vec3 o_direction;
+
+layout(push_constant) uniform PushConstants {
+    mat4 inverse_view_proj; // without the camera's translation
+    vec4 zenith_color;
+    vec4 horizon_color;
+    vec4 sun_direction; // towards the sun
+    vec4 sun_color; // w is 1.0 if the sun's disc should be drawn
+} push;
+
+void main() {
+    // Unproject a point on the far plane to get the direction this pixel looks in
+    vec4 far = push.inverse_view_proj * vec4(i_position, 1.0, 1.0);
+    o_direction = far.xyz / far.w;
+
+    gl_Position = vec4(i_position, 0.0, 1.0);
+}
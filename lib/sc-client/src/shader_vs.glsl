@@ -5,15 +5,24 @@
 
 layout(location = 0) in vec3 i_position;
 layout(location = 1) in vec3 i_normal;
+layout(location = 2) in vec2 i_uv;
+layout(location = 3) in mat4 i_model;
 
 layout(location = 0) out vec3 o_normal;
+layout(location = 1) out vec3 o_position;
+layout(location = 2) out vec2 o_uv;
 
 layout(set = 0, binding = 0) uniform Data {
-    mat4 worldview;
+    mat4 view;
     mat4 proj;
 } uniforms;
 
 void main() {
-    o_normal = transpose(inverse(mat3(uniforms.worldview))) * i_normal;
-    gl_Position = uniforms.proj * uniforms.worldview * vec4(i_position, 1.0);
+    mat4 worldview = uniforms.view * i_model;
+    vec4 position = worldview * vec4(i_position, 1.0);
+
+    o_normal = transpose(inverse(mat3(worldview))) * i_normal;
+    o_position = position.xyz;
+    o_uv = i_uv;
+    gl_Position = uniforms.proj * position;
 }
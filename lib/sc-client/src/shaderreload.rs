@@ -0,0 +1,425 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use glsl_to_spirv::{self, ShaderType};
+use vulkano::device::Device;
+use vulkano::pipeline::shader::ShaderModule;
+
+use error::FrontendError;
+
+/// How often the shader directory is checked for changes.
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// Compiles a GLSL shader from its source file into a shader module, checking that its interface
+/// still matches the SPIR-V the build script compiled from the same file.
+pub fn compile(device: &Arc<Device>, path: &Path, ty: ShaderType, built: &[u8])
+               -> Result<Arc<ShaderModule>, FrontendError> {
+    let path_str = path.display().to_string();
+
+    let mut source = String::new();
+    try!(File::open(path).and_then(|mut f| f.read_to_string(&mut source))
+        .map_err(|e| FrontendError::ShaderSource(path_str.clone(), e)));
+
+    let mut spirv = Vec::new();
+    try!(glsl_to_spirv::compile(&source, ty)
+        .and_then(|mut f| f.read_to_end(&mut spirv).map_err(|e| e.to_string()))
+        .map_err(|e| FrontendError::ShaderCompile(path_str.clone(), e)));
+
+    // The pipeline layouts and vertex definitions are written against the built shader, a module
+    // that doesn't line up with them can't be swapped in
+    let interface = try!(ShaderInterface::reflect(&spirv)
+        .map_err(|e| FrontendError::ShaderCompile(path_str.clone(), e)));
+    let built_interface = try!(ShaderInterface::reflect(built)
+        .map_err(|e| FrontendError::ShaderCompile(path_str.clone(), e)));
+    let differences = interface.differences(&built_interface);
+    if !differences.is_empty() {
+        return Err(FrontendError::ShaderInterface(path_str, differences.join(" and ")));
+    }
+
+    unsafe { ShaderModule::new(device, &spirv) }.map_err(FrontendError::Shader)
+}
+
+const SPIRV_MAGIC: u32 = 0x07230203;
+const SPIRV_HEADER_WORDS: usize = 5;
+
+const OP_TYPE_VOID: u32 = 19;
+const OP_TYPE_BOOL: u32 = 20;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_STORAGE_BUFFER: u32 = 12;
+
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+/// What a pipeline relies on in a shader: its inputs and outputs by location, its descriptors by
+/// set and binding and its push constants.
+///
+/// Types are kept as descriptions rather than SPIR-V ids, which change with every compile. Struct
+/// descriptions include the member offsets, so reordering a uniform block counts as a change
+/// while renaming one doesn't.
+#[derive(Debug, PartialEq)]
+pub struct ShaderInterface {
+    inputs: Vec<(u32, String)>,
+    outputs: Vec<(u32, String)>,
+    descriptors: Vec<((u32, u32), String)>,
+    push_constants: Vec<String>,
+}
+
+impl ShaderInterface {
+    /// Reads the interface from a SPIR-V module.
+    pub fn reflect(spirv: &[u8]) -> Result<Self, String> {
+        let words = try!(spirv_words(spirv));
+        let module = try!(SpirvTypes::parse(&words[SPIRV_HEADER_WORDS..]));
+
+        let mut interface = ShaderInterface {
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+            descriptors: Vec::new(),
+            push_constants: Vec::new(),
+        };
+        for &(id, storage, pointer) in &module.variables {
+            let ty = module.describe(pointer);
+            let location = module.decoration(id, DECORATION_LOCATION);
+            let binding = (
+                module.decoration(id, DECORATION_DESCRIPTOR_SET),
+                module.decoration(id, DECORATION_BINDING)
+            );
+
+            // Built-ins like gl_Position don't have a location and are always there
+            match (storage, location, binding) {
+                (STORAGE_INPUT, Some(location), _) => interface.inputs.push((location, ty)),
+                (STORAGE_OUTPUT, Some(location), _) => interface.outputs.push((location, ty)),
+                (STORAGE_UNIFORM_CONSTANT, _, (Some(set), Some(binding))) |
+                (STORAGE_UNIFORM, _, (Some(set), Some(binding))) |
+                (STORAGE_STORAGE_BUFFER, _, (Some(set), Some(binding))) =>
+                    interface.descriptors.push(((set, binding), format!("{} {}", storage, ty))),
+                (STORAGE_PUSH_CONSTANT, _, _) => interface.push_constants.push(ty),
+                _ => (),
+            }
+        }
+
+        interface.inputs.sort();
+        interface.outputs.sort();
+        interface.descriptors.sort();
+        interface.push_constants.sort();
+        Ok(interface)
+    }
+
+    /// Names the parts of the interface that differ from the other one.
+    pub fn differences(&self, other: &ShaderInterface) -> Vec<&'static str> {
+        let mut differences = Vec::new();
+        if self.inputs != other.inputs {
+            differences.push("inputs");
+        }
+        if self.outputs != other.outputs {
+            differences.push("outputs");
+        }
+        if self.descriptors != other.descriptors {
+            differences.push("descriptors");
+        }
+        if self.push_constants != other.push_constants {
+            differences.push("push constants");
+        }
+        differences
+    }
+}
+
+fn spirv_words(spirv: &[u8]) -> Result<Vec<u32>, String> {
+    if spirv.len() % 4 != 0 {
+        return Err("SPIR-V isn't made of whole words".to_string());
+    }
+
+    let words: Vec<u32> = spirv.chunks(4)
+        .map(|b| b[0] as u32 | (b[1] as u32) << 8 | (b[2] as u32) << 16 | (b[3] as u32) << 24)
+        .collect();
+    if words.len() < SPIRV_HEADER_WORDS || words[0] != SPIRV_MAGIC {
+        return Err("SPIR-V header is missing".to_string());
+    }
+
+    Ok(words)
+}
+
+/// The types, variables and decorations of a SPIR-V module, everything else is skipped.
+struct SpirvTypes {
+    types: HashMap<u32, (u32, Vec<u32>)>, // id, (opcode, operands after the id)
+    constants: HashMap<u32, u32>, // id, first word of the value
+    variables: Vec<(u32, u32, u32)>, // id, storage class, pointer type
+    decorations: HashMap<(u32, u32), u32>, // (id, decoration), first parameter
+    offsets: HashMap<(u32, u32), u32>, // (struct id, member), offset
+}
+
+impl SpirvTypes {
+    fn parse(mut words: &[u32]) -> Result<Self, String> {
+        let mut module = SpirvTypes {
+            types: HashMap::new(),
+            constants: HashMap::new(),
+            variables: Vec::new(),
+            decorations: HashMap::new(),
+            offsets: HashMap::new(),
+        };
+
+        while !words.is_empty() {
+            let count = (words[0] >> 16) as usize;
+            let opcode = words[0] & 0xffff;
+            if count == 0 || count > words.len() || count - 1 < min_operands(opcode) {
+                return Err(format!("SPIR-V instruction {} is incomplete", opcode));
+            }
+            let operands = &words[1..count];
+
+            match opcode {
+                _ if is_type(opcode) => {
+                    module.types.insert(operands[0], (opcode, operands[1..].to_vec()));
+                },
+                OP_CONSTANT => { module.constants.insert(operands[1], operands[2]); },
+                OP_VARIABLE => module.variables.push((operands[1], operands[2], operands[0])),
+                OP_DECORATE => {
+                    let parameter = operands.get(2).cloned().unwrap_or(0);
+                    module.decorations.insert((operands[0], operands[1]), parameter);
+                },
+                OP_MEMBER_DECORATE if operands[2] == DECORATION_OFFSET && operands.len() > 3 => {
+                    module.offsets.insert((operands[0], operands[1]), operands[3]);
+                },
+                _ => (),
+            }
+
+            words = &words[count..];
+        }
+
+        Ok(module)
+    }
+
+    fn decoration(&self, id: u32, decoration: u32) -> Option<u32> {
+        self.decorations.get(&(id, decoration)).cloned()
+    }
+
+    fn describe(&self, id: u32) -> String {
+        let &(opcode, ref operands) = match self.types.get(&id) {
+            Some(ty) => ty,
+            None => return format!("unknown type {}", id),
+        };
+
+        match opcode {
+            OP_TYPE_VOID => "void".to_string(),
+            OP_TYPE_BOOL => "bool".to_string(),
+            OP_TYPE_INT if operands[1] != 0 => format!("i{}", operands[0]),
+            OP_TYPE_INT => format!("u{}", operands[0]),
+            OP_TYPE_FLOAT => format!("f{}", operands[0]),
+            OP_TYPE_VECTOR | OP_TYPE_MATRIX =>
+                format!("{}x{}", self.describe(operands[0]), operands[1]),
+            OP_TYPE_IMAGE => format!("image<{}>{:?}", self.describe(operands[0]), &operands[1..]),
+            OP_TYPE_SAMPLER => "sampler".to_string(),
+            OP_TYPE_SAMPLED_IMAGE => format!("sampled {}", self.describe(operands[0])),
+            OP_TYPE_ARRAY => {
+                let length = self.constants.get(&operands[1]).cloned().unwrap_or(0);
+                format!("[{}; {}]", self.describe(operands[0]), length)
+            },
+            OP_TYPE_RUNTIME_ARRAY => format!("[{}]", self.describe(operands[0])),
+            OP_TYPE_STRUCT => {
+                let members: Vec<_> = operands.iter().enumerate()
+                    .map(|(i, &member)| match self.offsets.get(&(id, i as u32)) {
+                        Some(offset) => format!("{} @{}", self.describe(member), offset),
+                        None => self.describe(member),
+                    })
+                    .collect();
+                let kind = match self.decoration(id, DECORATION_BUFFER_BLOCK) {
+                    Some(_) => "buffer ",
+                    None => "",
+                };
+                format!("{}{{{}}}", kind, members.join(", "))
+            },
+            OP_TYPE_POINTER => self.describe(operands[1]),
+            _ => format!("type {}", opcode),
+        }
+    }
+}
+
+/// The fewest operands an instruction read by `SpirvTypes` has, anything shorter is malformed.
+fn min_operands(opcode: u32) -> usize {
+    match opcode {
+        OP_TYPE_FLOAT | OP_TYPE_SAMPLED_IMAGE | OP_TYPE_RUNTIME_ARRAY | OP_DECORATE => 2,
+        OP_TYPE_INT | OP_TYPE_VECTOR | OP_TYPE_MATRIX | OP_TYPE_ARRAY | OP_TYPE_POINTER |
+        OP_CONSTANT | OP_VARIABLE | OP_MEMBER_DECORATE => 3,
+        OP_TYPE_IMAGE => 8,
+        _ if is_type(opcode) => 1,
+        _ => 0,
+    }
+}
+
+fn is_type(opcode: u32) -> bool {
+    opcode >= OP_TYPE_VOID && opcode <= OP_TYPE_POINTER
+}
+
+/// Watches the GLSL files in a directory, reporting the ones that changed since the last poll.
+///
+/// Changes are found by comparing modification times, so this works the same everywhere
+/// without a file notification library.
+pub struct ShaderWatcher {
+    directory: PathBuf,
+    modified: HashMap<String, SystemTime>,
+    last_poll: Instant,
+}
+
+impl ShaderWatcher {
+    /// Starts watching the directory, the shaders already in it count as unchanged.
+    pub fn new(directory: &Path) -> Result<Self, FrontendError> {
+        let mut watcher = ShaderWatcher::unscanned(directory);
+        let files = try!(watcher.scan()
+            .map_err(|e| FrontendError::ShaderWatch(directory.display().to_string(), e)));
+        watcher.update(files);
+
+        Ok(watcher)
+    }
+
+    fn unscanned(directory: &Path) -> Self {
+        ShaderWatcher {
+            directory: directory.to_path_buf(),
+            modified: HashMap::new(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Returns the file names of the shaders that changed, checking the directory at most every
+    /// `POLL_INTERVAL_MS`.
+    pub fn poll(&mut self) -> Vec<String> {
+        if self.last_poll.elapsed() < Duration::from_millis(POLL_INTERVAL_MS) {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        // An editor might be halfway through saving, the next poll will pick it up
+        match self.scan() {
+            Ok(files) => self.update(files),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    fn scan(&self) -> ::std::io::Result<Vec<(String, SystemTime)>> {
+        let mut files = Vec::new();
+        for entry in try!(fs::read_dir(&self.directory)) {
+            let entry = try!(entry);
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if name.ends_with(".glsl") {
+                files.push((name, try!(try!(entry.metadata()).modified())));
+            }
+        }
+        Ok(files)
+    }
+
+    /// Records the modification times, returning the files that are new or have a different time.
+    fn update(&mut self, files: Vec<(String, SystemTime)>) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (name, modified) in files {
+            if self.modified.insert(name.clone(), modified) != Some(modified) {
+                changed.push(name);
+            }
+        }
+        changed.sort();
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+    use std::path::Path;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+    use glsl_to_spirv::{self, ShaderType};
+    use shaderreload::{ShaderWatcher, ShaderInterface};
+
+    const FRAGMENT: &'static str = "
+        #version 450
+
+        layout(location = 0) in vec2 i_uv;
+        layout(location = 0) out vec4 o_color;
+        layout(set = 0, binding = 0) uniform sampler2D u_texture;
+        layout(push_constant) uniform PushConstants { vec4 tint; } push;
+
+        void main() { o_color = texture(u_texture, i_uv) * push.tint; }
+    ";
+
+    fn at(seconds: u64) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(seconds)
+    }
+
+    #[test]
+    fn missing_directory_is_an_error() {
+        assert!(ShaderWatcher::new(Path::new("does-not-exist")).is_err());
+    }
+
+    #[test]
+    fn reports_changed_files() {
+        let mut watcher = ShaderWatcher::unscanned(Path::new("does-not-exist"));
+        let files = |fs_time| vec![
+            ("shader_vs.glsl".to_string(), at(10)),
+            ("shader_fs.glsl".to_string(), at(fs_time)),
+        ];
+
+        assert_eq!(watcher.update(files(10)), vec!["shader_fs.glsl", "shader_vs.glsl"]);
+        assert!(watcher.update(files(10)).is_empty());
+        assert_eq!(watcher.update(files(12)), vec!["shader_fs.glsl"]);
+    }
+
+    fn interface(source: &str) -> ShaderInterface {
+        let mut spirv = Vec::new();
+        glsl_to_spirv::compile(source, ShaderType::Fragment).unwrap()
+            .read_to_end(&mut spirv).unwrap();
+        ShaderInterface::reflect(&spirv).unwrap()
+    }
+
+    #[test]
+    fn interface_ignores_the_shader_body() {
+        let built = interface(FRAGMENT);
+        let edited = interface(&FRAGMENT.replace("* push.tint", "+ push.tint * 0.5"));
+        assert!(edited.differences(&built).is_empty());
+    }
+
+    #[test]
+    fn interface_reports_what_changed() {
+        let built = interface(FRAGMENT);
+
+        let moved = interface(&FRAGMENT.replace("location = 0) in", "location = 1) in"));
+        assert_eq!(moved.differences(&built), vec!["inputs"]);
+
+        let rebound = interface(&FRAGMENT.replace("binding = 0", "binding = 1"));
+        assert_eq!(rebound.differences(&built), vec!["descriptors"]);
+
+        let grown = interface(&FRAGMENT.replace("vec4 tint;", "vec4 tint; float fade;"));
+        assert_eq!(grown.differences(&built), vec!["push constants"]);
+    }
+
+    #[test]
+    fn interface_needs_spirv() {
+        assert!(ShaderInterface::reflect(b"#version 450").is_err());
+        assert!(ShaderInterface::reflect(&[1, 2, 3]).is_err());
+    }
+}
@@ -0,0 +1,389 @@
+use std::sync::Arc;
+
+use cgmath::{self, Matrix4, Vector3, Vector4, Point3, Rad, Angle, InnerSpace, EuclideanSpace, Zero};
+use vulkano::command_buffer::{DynamicState, PrimaryCommandBufferBuilder};
+use vulkano::buffer::cpu_access::CpuAccessibleBuffer;
+use vulkano::device::Device;
+use vulkano::format::Format;
+use vulkano::framebuffer::{Framebuffer, Subpass};
+use vulkano::image::attachment::AttachmentImage;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineParams};
+use vulkano::pipeline::blend::Blend;
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::input_assembly::InputAssembly;
+use vulkano::pipeline::multisample::Multisample;
+use vulkano::pipeline::raster::{Rasterization, CullMode, DepthBiasControl, DepthBias};
+use vulkano::pipeline::vertex::OneVertexOneInstanceDefinition;
+use vulkano::pipeline::viewport::{ViewportsState, Viewport, Scissor};
+use vulkano::sampler::{Sampler, Filter, MipmapMode, SamplerAddressMode};
+
+use error::FrontendError;
+use mesh::{Mesh, IndexSlice, Vertex, Instance};
+use settings::GraphicsSettings;
+use {shadow_vs, shadow_fs};
+
+/// Has to match the define in the fragment shader.
+pub const MAX_CASCADES: usize = 4;
+
+/// How far towards the sun objects outside of a cascade still cast shadows into it.
+const CASTER_DISTANCE: f32 = 50.0;
+
+/// Blend between logarithmic and uniform cascade splits, higher values give the cascades close
+/// to the camera more detail.
+const SPLIT_LAMBDA: f32 = 0.75;
+
+mod renderpass {
+    single_pass_renderpass!{
+        attachments: {
+            depth: {
+                load: Clear,
+                store: Store,
+                format: ::vulkano::format::Format,
+            }
+        },
+        pass: {
+            color: [],
+            depth_stencil: {depth}
+        }
+    }
+}
+
+mod pipeline_layout {
+    pipeline_layout!{
+        push_constants: {
+            light_view_proj: [[f32; 4]; 4]
+        }
+    }
+}
+
+type ShadowPipeline = GraphicsPipeline<OneVertexOneInstanceDefinition<Vertex, Instance>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>;
+
+/// A single cascade of the sun's shadow, covering a slice of the camera's view.
+#[derive(Copy, Clone, Debug)]
+pub struct Cascade {
+    /// The distance from the camera at which this cascade ends.
+    pub split: f32,
+    /// Transforms world space positions to the cascade's clip space.
+    pub view_proj: Matrix4<f32>,
+}
+
+/// Calculates the cascades covering the camera's view up to the shadow distance.
+pub fn cascades(settings: &GraphicsSettings, camera: &Matrix4<f32>, aspect: f32,
+                sun_direction: Vector3<f32>) -> Vec<Cascade> {
+    let far = settings.far.min(settings.shadow_distance);
+    let splits = cascade_splits(settings.near, far, settings.shadow_cascades as usize, SPLIT_LAMBDA);
+
+    let mut near = settings.near;
+    splits.iter().map(|&split| {
+        let corners = frustum_corners(camera, Rad::from(cgmath::Deg(settings.fov)), aspect, near, split);
+        near = split;
+
+        Cascade {
+            split: split,
+            view_proj: cascade_matrix(sun_direction, &corners, settings.shadow_resolution, CASTER_DISTANCE),
+        }
+    }).collect()
+}
+
+/// Splits the range between near and far into slices, returning the far distance of every slice.
+/// Lambda blends between uniform splits at 0.0 and logarithmic splits at 1.0.
+pub fn cascade_splits(near: f32, far: f32, count: usize, lambda: f32) -> Vec<f32> {
+    (1..count + 1).map(|i| {
+        let fraction = i as f32 / count as f32;
+        let log = near * (far / near).powf(fraction);
+        let uniform = near + (far - near) * fraction;
+        lambda * log + (1.0 - lambda) * uniform
+    }).collect()
+}
+
+/// Calculates the world space corners of a slice of the camera's view, near corners first.
+pub fn frustum_corners(camera: &Matrix4<f32>, fov: Rad<f32>, aspect: f32, near: f32, far: f32)
+                       -> [Vector3<f32>; 8] {
+    let tan = (fov * 0.5).tan();
+
+    let mut corners = [Vector3::zero(); 8];
+    for (i, &depth) in [near, far].iter().enumerate() {
+        let y = depth * tan;
+        let x = y * aspect;
+        for (j, &(sx, sy)) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)].iter().enumerate() {
+            corners[i * 4 + j] = (*camera * Vector4::new(x * sx, y * sy, -depth, 1.0)).truncate();
+        }
+    }
+
+    corners
+}
+
+/// Calculates the sun's view-projection for a shadow map covering all corners, with depth in
+/// Vulkan's 0.0 to 1.0 range. Objects up to the caster distance towards the sun are included.
+///
+/// The covered area is a sphere around the corners snapped to whole texels, so it keeps its size
+/// when the camera rotates and only moves in texel steps, which stops shadow edges from
+/// shimmering.
+pub fn cascade_matrix(direction: Vector3<f32>, corners: &[Vector3<f32>; 8], resolution: u32,
+                      caster_distance: f32) -> Matrix4<f32> {
+    let center = corners.iter().fold(Vector3::zero(), |a, &c| a + c) / 8.0;
+    let radius = corners.iter().map(|&c| (c - center).magnitude()).fold(0.0, f32::max);
+    let radius = (radius * 16.0).ceil() / 16.0;
+
+    // Look along the light's direction, with any up vector that isn't parallel to it
+    let direction = direction.normalize();
+    let up = if direction.y.abs() > 0.99 { Vector3::unit_z() } else { Vector3::unit_y() };
+    let view = Matrix4::look_at(Point3::origin(), Point3::from_vec(direction), up);
+
+    let texel = 2.0 * radius / resolution as f32;
+    let light_center = (view * center.extend(1.0)).truncate();
+    let x = (light_center.x / texel).floor() * texel;
+    let y = (light_center.y / texel).floor() * texel;
+    let distance = -light_center.z;
+
+    let proj = cgmath::ortho(
+        x - radius, x + radius, y - radius, y + radius,
+        distance - radius - caster_distance, distance + radius
+    );
+
+    vulkan_depth() * proj * view
+}
+
+/// Converts the cascade's clip space to its part of the shadow map, with the cascades placed
+/// side by side.
+pub fn atlas_matrix(cascade: usize, count: usize) -> Matrix4<f32> {
+    let count = count as f32;
+    Matrix4::new(
+        0.5 / count, 0.0, 0.0, 0.0,
+        0.0, 0.5, 0.0, 0.0,
+        0.0, 0.0, 1.0, 0.0,
+        (0.5 + cascade as f32) / count, 0.5, 0.0, 1.0,
+    )
+}
+
+/// Converts OpenGL's -1.0 to 1.0 clip space depth to Vulkan's 0.0 to 1.0.
+fn vulkan_depth() -> Matrix4<f32> {
+    Matrix4::new(
+        1.0, 0.0, 0.0, 0.0,
+        0.0, 1.0, 0.0, 0.0,
+        0.0, 0.0, 0.5, 0.0,
+        0.0, 0.0, 0.5, 1.0,
+    )
+}
+
+/// A shadow map for one frame in flight, with all cascades side by side.
+pub struct ShadowMap {
+    image: Arc<AttachmentImage<Format>>,
+    framebuffer: Arc<Framebuffer<renderpass::CustomRenderPass>>,
+}
+
+impl ShadowMap {
+    pub fn image(&self) -> &Arc<AttachmentImage<Format>> {
+        &self.image
+    }
+}
+
+/// Renders the depth of the scene from the sun's perspective.
+pub struct ShadowPass {
+    enabled: bool,
+    resolution: u32,
+    cascades: usize,
+    renderpass: Arc<renderpass::CustomRenderPass>,
+    layout: Arc<pipeline_layout::CustomPipeline>,
+    pipeline: Arc<ShadowPipeline>,
+    sampler: Arc<Sampler>,
+}
+
+impl ShadowPass {
+    pub fn new(device: &Arc<Device>, settings: &GraphicsSettings) -> Result<Self, FrontendError> {
+        let vs = try!(shadow_vs::Shader::load(device).map_err(FrontendError::Shader));
+        let fs = try!(shadow_fs::Shader::load(device).map_err(FrontendError::Shader));
+
+        let renderpass = try!(renderpass::CustomRenderPass::new(device, &renderpass::Formats {
+            depth: (Format::D16Unorm, 1),
+        }).map_err(FrontendError::RenderPass));
+
+        let layout = try!(pipeline_layout::CustomPipeline::new(device)
+            .map_err(FrontendError::PipelineLayout));
+
+        let pipeline = try!(create_pipeline(device, &renderpass, &layout, &vs, &fs));
+
+        // Depth is compared manually in the shader, filtering depth values would be meaningless
+        let sampler = try!(Sampler::new(
+            device, Filter::Nearest, Filter::Nearest, MipmapMode::Nearest,
+            SamplerAddressMode::ClampToEdge, SamplerAddressMode::ClampToEdge,
+            SamplerAddressMode::ClampToEdge, 0.0, 1.0, 0.0, 0.0
+        ).map_err(FrontendError::Sampler));
+
+        Ok(ShadowPass {
+            enabled: settings.shadows,
+            resolution: settings.shadow_resolution,
+            cascades: settings.shadow_cascades as usize,
+            renderpass: renderpass,
+            layout: layout,
+            pipeline: pipeline,
+            sampler: sampler,
+        })
+    }
+
+    /// Switches to shaders reloaded at runtime. The old pipeline is only dropped once the new one
+    /// was created.
+    pub fn reload(&mut self, device: &Arc<Device>, vs: &shadow_vs::Shader, fs: &shadow_fs::Shader)
+                  -> Result<(), FrontendError> {
+        self.pipeline = try!(create_pipeline(device, &self.renderpass, &self.layout, vs, fs));
+        Ok(())
+    }
+
+    /// Creates a shadow map to render to, if shadows are disabled this is a placeholder that
+    /// will never be drawn to.
+    pub fn create_map(&self, device: &Arc<Device>) -> Result<ShadowMap, FrontendError> {
+        let dimensions = if self.enabled {
+            [self.resolution * self.cascades as u32, self.resolution]
+        } else {
+            [1, 1]
+        };
+
+        let image = try!(AttachmentImage::new(device, dimensions, Format::D16Unorm)
+            .map_err(FrontendError::Image));
+        let framebuffer = try!(Framebuffer::new(
+            &self.renderpass, [dimensions[0], dimensions[1], 1],
+            renderpass::AList { depth: &image }
+        ).map_err(FrontendError::Framebuffer));
+
+        Ok(ShadowMap {
+            image: image,
+            framebuffer: framebuffer,
+        })
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
+
+    /// Records drawing every mesh with its instances into every cascade of the shadow map.
+    pub fn draw(&self, builder: PrimaryCommandBufferBuilder, map: &ShadowMap,
+                cascades: &[Cascade], draws: &[(&Mesh, &Arc<CpuAccessibleBuffer<[Instance]>>)])
+                -> PrimaryCommandBufferBuilder {
+        if !self.enabled {
+            return builder;
+        }
+
+        let mut inline = builder.draw_inline(&self.renderpass, &map.framebuffer, renderpass::ClearValues {
+            depth: 1.0,
+        });
+
+        for (i, cascade) in cascades.iter().enumerate() {
+            let origin = [(i as u32 * self.resolution) as i32, 0];
+            let dimensions = [self.resolution, self.resolution];
+            let dynamic = DynamicState {
+                line_width: None,
+                viewports: Some(vec![Viewport {
+                    origin: [origin[0] as f32, 0.0],
+                    dimensions: [dimensions[0] as f32, dimensions[1] as f32],
+                    depth_range: 0.0 .. 1.0,
+                }]),
+                scissors: Some(vec![Scissor {
+                    origin: origin,
+                    dimensions: dimensions,
+                }]),
+            };
+            let constants = pipeline_layout::PushConstants {
+                light_view_proj: cascade.view_proj.into(),
+            };
+
+            for &(mesh, instances) in draws {
+                let vertices = (mesh.vertex_buffer(), instances);
+                inline = match mesh.indices() {
+                    IndexSlice::U16(indices) =>
+                        inline.draw_indexed(&self.pipeline, vertices, indices, &dynamic, (), &constants),
+                    IndexSlice::U32(indices) =>
+                        inline.draw_indexed(&self.pipeline, vertices, indices, &dynamic, (), &constants),
+                };
+            }
+        }
+
+        inline.draw_end()
+    }
+}
+
+fn create_pipeline(
+    device: &Arc<Device>, renderpass: &Arc<renderpass::CustomRenderPass>,
+    layout: &Arc<pipeline_layout::CustomPipeline>, vs: &shadow_vs::Shader, fs: &shadow_fs::Shader
+) -> Result<Arc<ShadowPipeline>, FrontendError> {
+    GraphicsPipeline::new(device, GraphicsPipelineParams {
+        vertex_input: OneVertexOneInstanceDefinition::new(),
+        vertex_shader: vs.main_entry_point(),
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        // Every cascade is drawn to its own part of the shadow map
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Rasterization {
+            // Both sides cast shadows, so thin and open meshes still block the sun
+            cull_mode: CullMode::None,
+            depth_bias: DepthBiasControl::Static(DepthBias {
+                constant_factor: 1.25,
+                clamp: 0.0,
+                slope_factor: 1.75,
+            }),
+            .. Default::default()
+        },
+        multisample: Multisample::disabled(),
+        fragment_shader: fs.main_entry_point(),
+        depth_stencil: DepthStencil::simple_depth_test(),
+        blend: Blend::pass_through(),
+        layout: layout,
+        render_pass: Subpass::from(renderpass, 0).unwrap(),
+    }).map_err(FrontendError::Pipeline)
+}
+
+#[cfg(test)]
+mod tests {
+    use cgmath::{Matrix4, Vector3, Rad, Angle, InnerSpace};
+    use shadow::{self, MAX_CASCADES};
+
+    #[test]
+    fn splits_end_at_far_and_increase() {
+        let uniform = shadow::cascade_splits(1.0, 100.0, 3, 0.0);
+        assert!((uniform[0] - 34.0).abs() < 0.001);
+        assert!((uniform[1] - 67.0).abs() < 0.001);
+
+        let log = shadow::cascade_splits(1.0, 100.0, MAX_CASCADES, 0.75);
+        assert!((log[MAX_CASCADES - 1] - 100.0).abs() < 0.001);
+        assert!(log.windows(2).all(|w| w[0] < w[1]));
+
+        // Logarithmic splits give the first cascade a smaller slice
+        assert!(log[0] < 100.0 / MAX_CASCADES as f32);
+    }
+
+    #[test]
+    fn cascade_matrix_contains_slice() {
+        let camera = Matrix4::from_translation(Vector3::new(3.0, 1.0, 2.0)) *
+            Matrix4::from_angle_y(Rad::full_turn() * 0.1);
+        let corners = shadow::frustum_corners(&camera, Rad::full_turn() * 0.25, 16.0 / 9.0, 0.1, 10.0);
+        let direction = Vector3::new(-0.3, -1.0, 0.2).normalize();
+
+        let matrix = shadow::cascade_matrix(direction, &corners, 1024, 5.0);
+
+        for corner in corners.iter() {
+            let clip = matrix * corner.extend(1.0);
+            assert!(clip.x.abs() <= 1.0 && clip.y.abs() <= 1.0, "{:?} outside", clip);
+            assert!(clip.z >= 0.0 && clip.z <= 1.0, "{:?} outside", clip);
+        }
+
+        // Casters between the slice and the sun have to be in front of the slice
+        let center = corners.iter().fold(Vector3::new(0.0, 0.0, 0.0), |a, &c| a + c) / 8.0;
+        let caster = matrix * (center - direction * 4.0).extend(1.0);
+        let receiver = matrix * center.extend(1.0);
+        assert!(caster.z >= 0.0 && caster.z < receiver.z);
+    }
+
+    #[test]
+    fn frustum_corners_follow_camera() {
+        let camera = Matrix4::from_translation(Vector3::new(0.0, 0.0, 5.0));
+        let corners = shadow::frustum_corners(&camera, Rad::full_turn() * 0.25, 1.0, 1.0, 2.0);
+
+        // A 90 degree FOV makes the slice as wide as it's deep
+        assert!((corners[0] - Vector3::new(-1.0, -1.0, 4.0)).magnitude() < 0.0001);
+        assert!((corners[6] - Vector3::new(2.0, 2.0, 3.0)).magnitude() < 0.0001);
+    }
+}
@@ -0,0 +1,134 @@
+use std::sync::Arc;
+
+use cgmath::{Matrix4, Vector4, SquareMatrix, InnerSpace};
+use vulkano::buffer::immutable::ImmutableBuffer;
+use vulkano::command_buffer::{DynamicState, PrimaryCommandBufferBuilderInlineDraw};
+use vulkano::device::{Device, Queue};
+use vulkano::framebuffer::Subpass;
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineParams};
+use vulkano::pipeline::blend::Blend;
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::input_assembly::InputAssembly;
+use vulkano::pipeline::multisample::Multisample;
+use vulkano::pipeline::raster::{Rasterization, CullMode};
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::viewport::ViewportsState;
+
+use sc_client_game::ClientWorld;
+use error::FrontendError;
+use frontend::renderpass;
+use mesh;
+use {sky_vs, sky_fs};
+
+mod pipeline_layout {
+    pipeline_layout!{
+        push_constants: {
+            inverse_view_proj: [[f32; 4]; 4],
+            zenith_color: [f32; 4],
+            horizon_color: [f32; 4],
+            sun_direction: [f32; 4],
+            sun_color: [f32; 4]
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+pub struct SkyVertex {
+    i_position: [f32; 2],
+}
+
+impl_vertex!(SkyVertex, i_position);
+
+type SkyPipeline = GraphicsPipeline<SingleBufferDefinition<SkyVertex>, pipeline_layout::CustomPipeline, renderpass::CustomRenderPass>;
+
+/// Draws the sky as a gradient between the horizon and zenith colors behind everything else.
+pub struct SkyPass {
+    vertex_buffer: Arc<ImmutableBuffer<[SkyVertex]>>,
+    renderpass: Arc<renderpass::CustomRenderPass>,
+    layout: Arc<pipeline_layout::CustomPipeline>,
+    pipeline: Arc<SkyPipeline>,
+}
+
+impl SkyPass {
+    pub fn new(device: &Arc<Device>, queue: &Arc<Queue>,
+               renderpass: &Arc<renderpass::CustomRenderPass>) -> Result<Self, FrontendError> {
+        // A single triangle covering the whole screen
+        let vertex_buffer = try!(mesh::upload_static(device, queue, &[
+            SkyVertex { i_position: [-1.0, -1.0] },
+            SkyVertex { i_position: [3.0, -1.0] },
+            SkyVertex { i_position: [-1.0, 3.0] },
+        ]));
+
+        let vs = try!(sky_vs::Shader::load(device).map_err(FrontendError::Shader));
+        let fs = try!(sky_fs::Shader::load(device).map_err(FrontendError::Shader));
+
+        let layout = try!(pipeline_layout::CustomPipeline::new(device)
+            .map_err(FrontendError::PipelineLayout));
+        let pipeline = try!(create_pipeline(device, renderpass, &layout, &vs, &fs));
+
+        Ok(SkyPass {
+            vertex_buffer: vertex_buffer,
+            renderpass: renderpass.clone(),
+            layout: layout,
+            pipeline: pipeline,
+        })
+    }
+
+    /// Replaces the pipeline with one using different shaders, keeping the current one if that
+    /// fails.
+    pub fn reload(&mut self, device: &Arc<Device>, vs: &sky_vs::Shader, fs: &sky_fs::Shader)
+                  -> Result<(), FrontendError> {
+        self.pipeline = try!(create_pipeline(device, &self.renderpass, &self.layout, vs, fs));
+        Ok(())
+    }
+
+    /// Records drawing the sky, this has to come before any other geometry. The dynamic state
+    /// sets the viewport.
+    pub fn draw(&self, builder: PrimaryCommandBufferBuilderInlineDraw, dynamic: &DynamicState,
+                view: &Matrix4<f32>, proj: &Matrix4<f32>, world: &ClientWorld)
+                -> PrimaryCommandBufferBuilderInlineDraw {
+        // Only the camera's rotation matters for the direction the sky is seen in
+        let mut rotation = *view;
+        rotation.w = Vector4::new(0.0, 0.0, 0.0, 1.0);
+        let inverse_view_proj = (*proj * rotation).invert().unwrap_or(Matrix4::identity());
+
+        let cycle = world.day_cycle();
+        let sun = &world.lighting().sun;
+        let disc = if world.atmosphere().sun_disc { 1.0 } else { 0.0 };
+
+        let constants = pipeline_layout::PushConstants {
+            inverse_view_proj: inverse_view_proj.into(),
+            zenith_color: cycle.sky_color().extend(1.0).into(),
+            horizon_color: cycle.horizon_color().extend(1.0).into(),
+            sun_direction: (-sun.direction.normalize()).extend(0.0).into(),
+            sun_color: (sun.color * sun.intensity).extend(disc).into(),
+        };
+
+        builder.draw(&self.pipeline, &self.vertex_buffer, dynamic, (), &constants)
+    }
+}
+
+fn create_pipeline(
+    device: &Arc<Device>, renderpass: &Arc<renderpass::CustomRenderPass>,
+    layout: &Arc<pipeline_layout::CustomPipeline>, vs: &sky_vs::Shader, fs: &sky_fs::Shader
+) -> Result<Arc<SkyPipeline>, FrontendError> {
+    GraphicsPipeline::new(device, GraphicsPipelineParams {
+        vertex_input: SingleBufferDefinition::new(),
+        vertex_shader: vs.main_entry_point(),
+        input_assembly: InputAssembly::triangle_list(),
+        tessellation: None,
+        geometry_shader: None,
+        viewport: ViewportsState::Dynamic { num: 1 },
+        raster: Rasterization {
+            cull_mode: CullMode::None,
+            .. Default::default()
+        },
+        multisample: Multisample::disabled(),
+        fragment_shader: fs.main_entry_point(),
+        // The sky is drawn first, so it doesn't need to touch the depth buffer at all
+        depth_stencil: DepthStencil::disabled(),
+        blend: Blend::pass_through(),
+        layout: layout,
+        render_pass: Subpass::from(renderpass, 0).unwrap(),
+    }).map_err(FrontendError::Pipeline)
+}
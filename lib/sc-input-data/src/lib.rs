@@ -2,18 +2,25 @@ extern crate cgmath;
 
 use cgmath::Vector2;
 
+/// How many buttons there are, every variant of `Button` before `__DoNotMatch`.
+const BUTTON_COUNT: usize = Button::__DoNotMatch as usize;
+
 pub struct InputState {
-    buttons: [bool; 8],
+    buttons: [bool; BUTTON_COUNT],
     mouse_position: Vector2<i32>,
     frame_mouse: Vector2<i32>,
+    frame_scroll: Vector2<f32>,
+    frame_text: Vec<TextInput>,
 }
 
 impl InputState {
     pub fn new() -> Self {
         InputState {
-            buttons: [false; 8],
+            buttons: [false; BUTTON_COUNT],
             mouse_position: Vector2::new(0, 0),
             frame_mouse: Vector2::new(0, 0),
+            frame_scroll: Vector2::new(0.0, 0.0),
+            frame_text: Vec::new(),
         }
     }
 
@@ -42,28 +49,88 @@ impl InputState {
         self.mouse_position = position;
     }
 
+    pub fn add_scroll(&mut self, lines: Vector2<f32>) {
+        self.frame_scroll += lines;
+    }
+
+    pub fn add_text(&mut self, text: TextInput) {
+        self.frame_text.push(text);
+    }
+
+    /// Lets go of every button and drops this frame's mouse motion, for when input stops
+    /// arriving and the matching releases never will.
+    pub fn release_all(&mut self) {
+        self.buttons = [false; BUTTON_COUNT];
+        self.frame_mouse = Vector2::new(0, 0);
+        self.frame_scroll = Vector2::new(0.0, 0.0);
+    }
+
     pub fn end_frame(&mut self) {
         self.frame_mouse = Vector2::new(0, 0);
+        self.frame_scroll = Vector2::new(0.0, 0.0);
+        self.frame_text.clear();
     }
 
     pub fn frame_mouse(&self) -> Vector2<i32> {
         self.frame_mouse
     }
+
+    /// How far the wheel turned this frame in lines, a weapon switch would step once per line.
+    pub fn frame_scroll(&self) -> Vector2<f32> {
+        self.frame_scroll
+    }
+
+    /// The text typed this frame, in the order it was typed.
+    pub fn frame_text(&self) -> &[TextInput] {
+        &self.frame_text
+    }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Button {
     MoveForward,
     MoveBackward,
     MoveRight,
     MoveLeft,
     Menu,
+    Fire,
+    Aim,
+    Console,
+    Chat,
+    Confirm,
     __DoNotMatch,
 }
 
+/// Typing into a text field, sent instead of buttons while the game has one focused.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TextInput {
+    Char(char),
+    Backspace,
+    Delete,
+    Enter,
+    Left,
+    Right,
+    Up,
+    Down,
+    Home,
+    End,
+}
+
+/// How the frontend treats the mouse cursor while it's over the window.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CursorMode {
+    /// Shown and free to leave the window, for menus.
+    Free,
+    /// Not drawn over the window, but otherwise left alone.
+    Hidden,
+    /// Kept inside the window and hidden, only its relative motion is used.
+    Grabbed,
+}
+
 #[cfg(test)]
 mod tests {
     use cgmath::Vector2;
-    use {InputState, Button};
+    use {InputState, Button, TextInput};
 
     #[test]
     fn set_changes_get_result() {
@@ -79,6 +146,16 @@ mod tests {
         assert!(input.get(Button::MoveBackward));
     }
 
+    #[test]
+    fn last_button_has_a_state() {
+        let mut input = InputState::new();
+
+        input.set(Button::Confirm, true);
+        assert!(input.get(Button::Confirm));
+        input.release_all();
+        assert!(!input.get(Button::Confirm));
+    }
+
     #[test]
     fn tracks_accumulated_mouse() {
         let mut input = InputState::new();
@@ -91,4 +168,43 @@ mod tests {
         input.add_mouse(Vector2::new(8,  5));
         assert_eq!(input.frame_mouse(), Vector2::new(16, 15));
     }
+
+    #[test]
+    fn scroll_resets_every_frame() {
+        let mut input = InputState::new();
+
+        input.add_scroll(Vector2::new(0.0, 1.0));
+        input.add_scroll(Vector2::new(0.0, 2.0));
+        assert_eq!(input.frame_scroll(), Vector2::new(0.0, 3.0));
+
+        input.end_frame();
+        assert_eq!(input.frame_scroll(), Vector2::new(0.0, 0.0));
+    }
+
+    #[test]
+    fn text_is_kept_in_order_for_one_frame() {
+        let mut input = InputState::new();
+
+        input.add_text(TextInput::Char('h'));
+        input.add_text(TextInput::Char('i'));
+        input.add_text(TextInput::Backspace);
+        assert_eq!(input.frame_text(), &[
+            TextInput::Char('h'), TextInput::Char('i'), TextInput::Backspace
+        ]);
+
+        input.end_frame();
+        assert!(input.frame_text().is_empty());
+    }
+
+    #[test]
+    fn release_all_clears_buttons_and_mouse() {
+        let mut input = InputState::new();
+
+        input.set(Button::MoveLeft, true);
+        input.add_mouse(Vector2::new(5, -3));
+        input.release_all();
+
+        assert!(!input.get(Button::MoveLeft));
+        assert_eq!(input.frame_mouse(), Vector2::new(0, 0));
+    }
 }
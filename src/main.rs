@@ -1,5 +1,7 @@
 extern crate sc_client;
 
+use std::process;
+
 fn main() {
-    sc_client::run();
+    process::exit(sc_client::run());
 }